@@ -0,0 +1,28 @@
+pub const MAX_BPS: u16 = 10_000;
+pub const DEFAULT_FLASH_FEE: u16 = 9;
+
+// LP mints take at most as many decimals as SOL
+pub const MAX_LP_DECIMALS: u8 = 9;
+
+// LP minted to the locked account on a pool's first deposit
+pub const MINIMUM_LIQUIDITY: u64 = 1_000;
+
+// StableSwap amplification bounds and how fast the authority may move it
+pub const MIN_AMP: u64 = 1;
+pub const MAX_AMP: u64 = 1_000_000;
+pub const MAX_AMP_CHANGE: u64 = 10;
+pub const MIN_RAMP_DURATION: i64 = 86_400;
+
+// dynamic fees: the volatility accumulator halves every this many slots, and every bps of
+// it adds this share (in bps) of a bps to the fee
+pub const VOLATILITY_HALF_LIFE: u64 = 150;
+pub const VOLATILITY_FEE_RATE: u16 = 1_000;
+
+// how long LPs get to withdraw once the authority schedules a forced close
+pub const FORCED_EXIT_WINDOW: i64 = 7 * 86_400;
+
+// orders expire within a forced exit's notice, so none can outlive the pool
+pub const MAX_ORDER_DURATION: i64 = FORCED_EXIT_WINDOW;
+
+// farm accumulators count rewards per staked LP token in units of 1e-12
+pub const REWARD_PRECISION: u128 = 1_000_000_000_000;
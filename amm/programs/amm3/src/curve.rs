@@ -0,0 +1,194 @@
+use anchor_lang::prelude::*;
+
+use crate::constants::MAX_BPS;
+use crate::error::AmmError;
+use crate::math::isqrt;
+
+// Constant-product (x * y = k) math. Every product is taken in u128 and every division
+// states which way it rounds; the direction is always the one that favours the pool.
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Rounding {
+    Down,
+    Up,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SwapResult {
+    pub deposit: u64,
+    pub withdraw: u64,
+    pub fee: u64,
+}
+
+pub fn mul_div(a: u64, b: u64, c: u64, rounding: Rounding) -> Result<u64> {
+    require!(c != 0, AmmError::ZeroBalance);
+    let product = (a as u128) * (b as u128);
+    let quotient = match rounding {
+        Rounding::Down => product / c as u128,
+        Rounding::Up => product.div_ceil(c as u128),
+    };
+    u64::try_from(quotient).map_err(|_| error!(AmmError::Overflow))
+}
+
+// exact-input swap: the fee is taken from the input (rounded up) and the output is
+// rounded down, so k over the reserves can only grow
+pub fn swap_exact_in(
+    reserve_in: u64,
+    reserve_out: u64,
+    amount: u64,
+    fee: u16,
+) -> Result<SwapResult> {
+    require!(reserve_in != 0 && reserve_out != 0, AmmError::ZeroBalance);
+    require!(fee < MAX_BPS, AmmError::InvalidFee);
+    require!(amount != 0, AmmError::InvalidAmount);
+    let fee_amount = mul_div(amount, fee as u64, MAX_BPS as u64, Rounding::Up)?;
+    let net_in = amount - fee_amount;
+    let new_in = reserve_in.checked_add(net_in).ok_or(AmmError::Overflow)?;
+    let withdraw = mul_div(reserve_out, net_in, new_in, Rounding::Down)?;
+    require!(withdraw != 0, AmmError::InvalidAmount);
+    Ok(SwapResult {
+        deposit: amount,
+        withdraw,
+        fee: fee_amount,
+    })
+}
+
+// input (fee included) needed to take exactly `amount_out` from the pool, rounded up
+// so the pool never gives out more than the curve allows; returns (amount_in, fee)
+pub fn exact_out_input(
+    reserve_in: u64,
+    reserve_out: u64,
+    amount_out: u64,
+    fee: u16,
+) -> Result<(u64, u64)> {
+    require!(amount_out < reserve_out, AmmError::Insufficientbalance);
+    require!(fee < MAX_BPS, AmmError::InvalidFee);
+    let net_in = mul_div(reserve_in, amount_out, reserve_out - amount_out, Rounding::Up)?;
+    let amount_in = mul_div(net_in, MAX_BPS as u64, (MAX_BPS - fee) as u64, Rounding::Up)?;
+    Ok((amount_in, amount_in - net_in))
+}
+
+// both sides a deposit of `amount` LP costs, rounded against the depositor
+pub fn deposit_amounts(
+    reserve_x: u64,
+    reserve_y: u64,
+    supply: u64,
+    amount: u64,
+) -> Result<(u64, u64)> {
+    Ok((
+        mul_div(amount, reserve_x, supply, Rounding::Up)?,
+        mul_div(amount, reserve_y, supply, Rounding::Up)?,
+    ))
+}
+
+// both sides paid out for burning `amount` LP, rounded against the withdrawer
+pub fn withdraw_amounts(
+    reserve_x: u64,
+    reserve_y: u64,
+    supply: u64,
+    amount: u64,
+) -> Result<(u64, u64)> {
+    require!(amount <= supply, AmmError::Insufficientbalance);
+    Ok((
+        mul_div(amount, reserve_x, supply, Rounding::Down)?,
+        mul_div(amount, reserve_y, supply, Rounding::Down)?,
+    ))
+}
+
+// LP for seeding an empty pool: the geometric mean of what the vaults receive, moved from
+// the mints' decimals to the LP's so that one whole LP stands for one whole x and y; rounded down
+pub fn initial_liquidity(
+    x: u64,
+    y: u64,
+    decimals_x: u8,
+    decimals_y: u8,
+    lp_decimals: u8,
+) -> Result<u64> {
+    let product = (x as u128) * (y as u128);
+    let exponent = 2 * lp_decimals as i32 - decimals_x as i32 - decimals_y as i32;
+    let scaled = match exponent >= 0 {
+        true => 10u128
+            .checked_pow(exponent as u32)
+            .and_then(|scale| product.checked_mul(scale))
+            .ok_or(AmmError::Overflow)?,
+        // a scale past u128 leaves nothing of the product
+        false => match 10u128.checked_pow(exponent.unsigned_abs()) {
+            Some(scale) => product / scale,
+            None => 0,
+        },
+    };
+    u64::try_from(isqrt(scaled)).map_err(|_| error!(AmmError::Overflow))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    // reserves and supplies stay below 2^30 so k * supply^2 fits a u128 in the checks
+    const MAX: u64 = 1 << 30;
+
+    fn k(x: u64, y: u64) -> u128 {
+        (x as u128) * (y as u128)
+    }
+
+    proptest! {
+        // the protocol fee leaves the vault, so k is checked with the whole fee taken out
+        #[test]
+        fn swap_never_decreases_k(
+            reserve_in in 1..MAX,
+            reserve_out in 1..MAX,
+            amount in 1..MAX,
+            fee in 0..1_000u16,
+        ) {
+            if let Ok(res) = swap_exact_in(reserve_in, reserve_out, amount, fee) {
+                prop_assert!(res.withdraw < reserve_out);
+                let after = k(reserve_in + amount - res.fee, reserve_out - res.withdraw);
+                prop_assert!(after >= k(reserve_in, reserve_out));
+            }
+        }
+
+        #[test]
+        fn exact_out_never_decreases_k(
+            reserve_in in 1..MAX,
+            reserve_out in 2..MAX,
+            amount_out in 1..MAX,
+            fee in 0..1_000u16,
+        ) {
+            prop_assume!(amount_out < reserve_out);
+            let (amount_in, fee_amount) =
+                exact_out_input(reserve_in, reserve_out, amount_out, fee).unwrap();
+            let after = k(reserve_in + amount_in - fee_amount, reserve_out - amount_out);
+            prop_assert!(after >= k(reserve_in, reserve_out));
+        }
+
+        // liquidity changes move k on purpose, what must not fall is k per LP share squared
+        #[test]
+        fn deposit_never_decreases_k_per_share(
+            reserve_x in 1..MAX,
+            reserve_y in 1..MAX,
+            supply in 1..MAX,
+            amount in 1..MAX,
+        ) {
+            prop_assume!(amount <= supply);
+            let (x, y) = deposit_amounts(reserve_x, reserve_y, supply, amount).unwrap();
+            let before = k(reserve_x, reserve_y) * k(supply + amount, supply + amount);
+            let after = k(reserve_x + x, reserve_y + y) * k(supply, supply);
+            prop_assert!(after >= before);
+        }
+
+        #[test]
+        fn withdraw_never_decreases_k_per_share(
+            reserve_x in 1..MAX,
+            reserve_y in 1..MAX,
+            supply in 1..MAX,
+            amount in 1..MAX,
+        ) {
+            prop_assume!(amount <= supply);
+            let (x, y) = withdraw_amounts(reserve_x, reserve_y, supply, amount).unwrap();
+            let before = k(reserve_x, reserve_y) * k(supply - amount, supply - amount);
+            let after = k(reserve_x - x, reserve_y - y) * k(supply, supply);
+            prop_assert!(after >= before);
+        }
+    }
+}
@@ -1,5 +1,4 @@
 use anchor_lang::error_code;
-use constant_product_curve::CurveError;
 
 #[error_code]
 pub enum AmmError {
@@ -39,18 +38,66 @@ pub enum AmmError {
     Insufficientbalance,
     #[msg("Zero balance")]
     ZeroBalance,
-}
-
-impl From<CurveError> for AmmError {
-    fn from(error: CurveError) -> AmmError {
-        match error {
-            CurveError::InvalidPrecision => AmmError::InvalidPrecision,
-            CurveError::Overflow => AmmError::Overflow,
-            CurveError::Underflow => AmmError::Underflow,
-            CurveError::InvalidFeeAmount => AmmError::InvalidFee,
-            CurveError::InsufficientBalance => AmmError::Insufficientbalance,
-            CurveError::ZeroBalance => AmmError::ZeroBalance,
-            CurveError::SlippageLimitExceeded => AmmError::SlippageExceded,
-        }
-    }
+    #[msg("Requested window is older than the oldest oracle observation")]
+    ObservationTooOld,
+    #[msg("Route accounts do not match the pools being swapped through")]
+    InvalidRoute,
+    #[msg("Mint has a token extension the pool does not support")]
+    UnsupportedMintExtension,
+    #[msg("Operation is not available for this pool type")]
+    InvalidPoolType,
+    #[msg("Invalid amplification coefficient or ramp")]
+    InvalidAmp,
+    #[msg("A flash loan is outstanding on this pool")]
+    FlashLoanActive,
+    #[msg("No flash loan is outstanding on this pool")]
+    NoFlashLoan,
+    #[msg("Flash loan is not repaid later in the transaction")]
+    FlashLoanNotRepaid,
+    #[msg("Pool mints must differ and be ordered mint_x < mint_y")]
+    InvalidMintOrder,
+    #[msg("Farm end time must be in the future and cannot be brought forward")]
+    InvalidFarmSchedule,
+    #[msg("Order has expired")]
+    OrderExpired,
+    #[msg("Pool price has not reached the order's limit")]
+    LimitNotReached,
+    #[msg("Launch has not reached its graduation target")]
+    LaunchTargetNotReached,
+    #[msg("The fee tier table is full")]
+    FeeTiersFull,
+    #[msg("Wallet is not on this pool's allowlist")]
+    NotAllowlisted,
+    #[msg("This pool is being closed")]
+    PoolClosing,
+    #[msg("Pool still has LP tokens outstanding")]
+    PoolNotDrained,
+    #[msg("Transaction Expired")]
+    TransactionExpired,
+    #[msg("The temporary wSOL account is passed in native SOL mode, and only then")]
+    NativeAccountMismatch,
+    #[msg("Pool still has open limit orders")]
+    OrdersOpen,
+    #[msg("Pool has a farm")]
+    FarmActive,
+    #[msg("Pool is not listed on this index page")]
+    PoolNotIndexed,
+    #[msg("The user's token account for this side is missing")]
+    MissingTokenAccount,
+    #[msg("Referral share is above the pool's referral fee")]
+    ReferralFeeTooHigh,
+    #[msg("Referrer cannot be owned by the trader")]
+    SelfReferral,
+    #[msg("Pool liquidity is locked for good")]
+    LiquidityLocked,
+    #[msg("Order expiry is too far out")]
+    OrderExpiryTooFar,
+    #[msg("Only the owner can cancel an order before it expires")]
+    OrderNotExpired,
+    #[msg("Only the owner can close a stake before a forced exit is due")]
+    ForcedExitNotDue,
+    #[msg("Farm still has open stakes")]
+    StakesOpen,
+    #[msg("Exit accounts are passed when LPs are left a claim on the pool, and only then")]
+    ExitAccountMismatch,
 }
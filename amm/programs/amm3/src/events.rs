@@ -0,0 +1,258 @@
+use anchor_lang::prelude::*;
+
+// reserves in these are the vault balances once the instruction is done
+#[event]
+pub struct Swapped {
+    pub config: Pubkey,
+    pub user: Pubkey,
+    pub is_x: bool,
+    pub amount_in: u64,
+    pub amount_out: u64,
+    pub fee: u64,
+    pub fee_bps: u16, // the fee rate charged, which moves with volatility in dynamic mode
+    pub protocol_fee: u64,
+    pub reserve_x: u64,
+    pub reserve_y: u64,
+}
+
+#[event]
+pub struct LiquidityAdded {
+    pub config: Pubkey,
+    pub user: Pubkey,
+    pub amount_x: u64,
+    pub amount_y: u64,
+    pub lp_amount: u64,
+    pub reserve_x: u64,
+    pub reserve_y: u64,
+}
+
+#[event]
+pub struct LiquidityRemoved {
+    pub config: Pubkey,
+    pub user: Pubkey,
+    pub amount_x: u64,
+    pub amount_y: u64,
+    pub lp_amount: u64,
+    pub reserve_x: u64,
+    pub reserve_y: u64,
+}
+
+#[event]
+pub struct ReferralPaid {
+    pub config: Pubkey,
+    pub user: Pubkey,
+    pub referrer: Pubkey,
+    pub mint: Pubkey,
+    pub amount: u64,
+}
+
+#[event]
+pub struct FeeUpdated {
+    pub config: Pubkey,
+    pub old_fee: u16,
+    pub new_fee: u16,
+}
+
+#[event]
+pub struct AllowlistUpdated {
+    pub config: Pubkey,
+    pub enabled: bool,
+}
+
+#[event]
+pub struct AllowlistEntryAdded {
+    pub config: Pubkey,
+    pub wallet: Pubkey,
+}
+
+#[event]
+pub struct AllowlistEntryRemoved {
+    pub config: Pubkey,
+    pub wallet: Pubkey,
+}
+
+#[event]
+pub struct DynamicFeeUpdated {
+    pub config: Pubkey,
+    pub enabled: bool,
+    pub min_fee: u16,
+    pub max_fee: u16,
+}
+
+#[event]
+pub struct FeeTierAdded {
+    pub fee: u16,
+}
+
+#[event]
+pub struct FeeTierRemoved {
+    pub fee: u16,
+}
+
+#[event]
+pub struct ProtocolFeeUpdated {
+    pub config: Pubkey,
+    pub old_protocol_fee: u16,
+    pub new_protocol_fee: u16,
+}
+
+#[event]
+pub struct ReferralFeeUpdated {
+    pub config: Pubkey,
+    pub old_referral_fee: u16,
+    pub new_referral_fee: u16,
+}
+
+#[event]
+pub struct ForcedExitStarted {
+    pub config: Pubkey,
+    pub close_after: i64,
+}
+
+// amounts are the protocol fees and the unclaimed vault balances swept to the authority's
+// token accounts; after a forced exit the vaults go to the exit accounts instead
+#[event]
+pub struct PoolClosed {
+    pub config: Pubkey,
+    pub amount_x: u64,
+    pub amount_y: u64,
+    pub exit_x: u64,
+    pub exit_y: u64,
+}
+
+#[event]
+pub struct Redeemed {
+    pub config: Pubkey,
+    pub user: Pubkey,
+    pub lp_amount: u64,
+    pub amount_x: u64,
+    pub amount_y: u64,
+}
+
+#[event]
+pub struct ProtocolFeesCollected {
+    pub config: Pubkey,
+    pub amount_x: u64,
+    pub amount_y: u64,
+}
+
+#[event]
+pub struct FlashFeeUpdated {
+    pub config: Pubkey,
+    pub old_flash_fee: u16,
+    pub new_flash_fee: u16,
+}
+
+#[event]
+pub struct FlashLoanTaken {
+    pub config: Pubkey,
+    pub borrower: Pubkey,
+    pub is_x: bool,
+    pub amount: u64,
+}
+
+#[event]
+pub struct FlashLoanRepaid {
+    pub config: Pubkey,
+    pub borrower: Pubkey,
+    pub is_x: bool,
+    pub amount: u64,
+    pub fee: u64,
+}
+
+#[event]
+pub struct AmpRampUpdated {
+    pub config: Pubkey,
+    pub amp_initial: u64,
+    pub amp_target: u64,
+    pub ramp_start: i64,
+    pub ramp_end: i64,
+}
+
+#[event]
+pub struct LockUpdated {
+    pub config: Pubkey,
+    pub locked: bool,
+}
+
+#[event]
+pub struct AuthorityUpdated {
+    pub config: Pubkey,
+    pub old_authority: Option<Pubkey>,
+    pub new_authority: Option<Pubkey>,
+}
+
+#[event]
+pub struct FarmFunded {
+    pub config: Pubkey,
+    pub farm: Pubkey,
+    pub amount: u64,
+    pub emission_rate: u64,
+    pub end_time: i64,
+}
+
+#[event]
+pub struct RewardsClaimed {
+    pub config: Pubkey,
+    pub farm: Pubkey,
+    pub user: Pubkey,
+    pub amount: u64,
+}
+
+#[event]
+pub struct StakeClosed {
+    pub config: Pubkey,
+    pub farm: Pubkey,
+    pub owner: Pubkey,
+    pub amount: u64,
+    pub rewards: u64,
+}
+
+#[event]
+pub struct FarmClosed {
+    pub config: Pubkey,
+    pub farm: Pubkey,
+    pub refunded: u64,
+}
+
+#[event]
+pub struct OrderPlaced {
+    pub config: Pubkey,
+    pub order: Pubkey,
+    pub owner: Pubkey,
+    pub is_x: bool,
+    pub amount_in: u64,
+    pub min_out: u64,
+    pub tip: u64,
+    pub expires_at: i64,
+}
+
+#[event]
+pub struct OrderFilled {
+    pub config: Pubkey,
+    pub order: Pubkey,
+    pub owner: Pubkey,
+    pub keeper: Pubkey,
+    pub amount_in: u64,
+    pub amount_out: u64,
+    pub tip: u64,
+}
+
+#[event]
+pub struct OrderCancelled {
+    pub config: Pubkey,
+    pub order: Pubkey,
+    pub owner: Pubkey,
+    pub caller: Pubkey,
+    pub amount_in: u64,
+}
+
+#[event]
+pub struct Graduated {
+    pub config: Pubkey,
+    pub amount_x: u64,
+    pub amount_y: u64,
+    pub surplus_x: u64, // sent to the treasury
+    pub surplus_y: u64,
+    pub lp_amount: u64,
+}
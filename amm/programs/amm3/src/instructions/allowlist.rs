@@ -0,0 +1,75 @@
+use crate::events::{AllowlistEntryAdded, AllowlistEntryRemoved};
+use crate::state::{AllowlistEntry, Config};
+use anchor_lang::prelude::*;
+
+#[derive(Accounts)]
+#[instruction(wallet: Pubkey)]
+pub struct AddAllowlistEntry<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    #[account(
+        seeds= [b"config", config.seed.to_le_bytes().as_ref()],
+        bump= config.config_bump,
+    )]
+    pub config: Box<Account<'info, Config>>,
+    #[account(
+        init,
+        payer = authority,
+        seeds = [b"allowlist", config.key().as_ref(), wallet.as_ref()],
+        bump,
+        space = 8 + AllowlistEntry::INIT_SPACE,
+    )]
+    pub entry: Box<Account<'info, AllowlistEntry>>,
+    pub system_program: Program<'info, System>,
+}
+
+impl<'info> AddAllowlistEntry<'info> {
+    pub fn add_to_allowlist(
+        &mut self,
+        wallet: Pubkey,
+        bumps: AddAllowlistEntryBumps,
+    ) -> Result<()> {
+        self.config.check_authority(&self.authority.key())?;
+        self.entry.set_inner(AllowlistEntry {
+            config: self.config.key(),
+            wallet,
+            bump: bumps.entry,
+        });
+        emit!(AllowlistEntryAdded {
+            config: self.config.key(),
+            wallet,
+        });
+        Ok(())
+    }
+}
+
+#[derive(Accounts)]
+pub struct RemoveAllowlistEntry<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    #[account(
+        seeds= [b"config", config.seed.to_le_bytes().as_ref()],
+        bump= config.config_bump,
+    )]
+    pub config: Box<Account<'info, Config>>,
+    // rent goes back to the authority
+    #[account(
+        mut,
+        close = authority,
+        has_one = config,
+        seeds = [b"allowlist", config.key().as_ref(), entry.wallet.as_ref()],
+        bump = entry.bump,
+    )]
+    pub entry: Box<Account<'info, AllowlistEntry>>,
+}
+
+impl<'info> RemoveAllowlistEntry<'info> {
+    pub fn remove_from_allowlist(&mut self) -> Result<()> {
+        self.config.check_authority(&self.authority.key())?;
+        emit!(AllowlistEntryRemoved {
+            config: self.config.key(),
+            wallet: self.entry.wallet,
+        });
+        Ok(())
+    }
+}
@@ -0,0 +1,111 @@
+use crate::error::AmmError;
+use crate::events::OrderCancelled;
+use crate::state::{Config, Order};
+use crate::utils::transfer_tokens;
+use anchor_lang::prelude::*;
+use anchor_spl::{
+    associated_token::AssociatedToken,
+    token_interface::{close_account, CloseAccount, Mint, TokenAccount, TokenInterface},
+};
+
+#[derive(Accounts)]
+pub struct CancelOrder<'info> {
+    #[account(mut)]
+    pub caller: Signer<'info>,
+    /// CHECK: only receives the order's rent and escrowed input, pinned by `has_one` on the order
+    #[account(mut)]
+    pub owner: UncheckedAccount<'info>,
+    #[account(
+        address = order.mint_in,
+        mint::token_program = token_program_in,
+    )]
+    pub mint_in: Box<InterfaceAccount<'info, Mint>>,
+    #[account(
+        mut,
+        seeds= [b"config", config.seed.to_le_bytes().as_ref()],
+        bump= config.config_bump,
+    )]
+    pub config: Box<Account<'info, Config>>,
+    // the tip and rent go back to the owner with the account
+    #[account(
+        mut,
+        close = owner,
+        has_one = config,
+        has_one = owner,
+        seeds = [b"order", config.key().as_ref(), owner.key().as_ref(), order.id.to_le_bytes().as_ref()],
+        bump = order.bump,
+    )]
+    pub order: Box<Account<'info, Order>>,
+    #[account(
+        mut,
+        associated_token::mint= mint_in,
+        associated_token::authority= order,
+        associated_token::token_program= token_program_in,
+    )]
+    pub escrow: Box<InterfaceAccount<'info, TokenAccount>>,
+    #[account(
+        init_if_needed,
+        payer = caller,
+        associated_token::mint= mint_in,
+        associated_token::authority= owner,
+        associated_token::token_program= token_program_in,
+    )]
+    pub owner_in: Box<InterfaceAccount<'info, TokenAccount>>,
+    pub token_program_in: Interface<'info, TokenInterface>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+}
+
+impl<'info> CancelOrder<'info> {
+    // open to the owner at any time, and to anyone once the order has expired, so a stale
+    // order cannot hold the pool open; either way the escrow goes back to the owner
+    pub fn cancel_order(&mut self) -> Result<()> {
+        require!(
+            self.caller.key() == self.owner.key()
+                || Clock::get()?.unix_timestamp > self.order.expires_at,
+            AmmError::OrderNotExpired
+        );
+        let amount_in = self.escrow.amount;
+        let config = self.config.key();
+        let owner = self.owner.key();
+        let id = self.order.id.to_le_bytes();
+        let seeds = &[
+            &b"order"[..],
+            config.as_ref(),
+            owner.as_ref(),
+            id.as_ref(),
+            &[self.order.bump],
+        ];
+        let signer_seeds = &[&seeds[..]];
+        if amount_in > 0 {
+            transfer_tokens(
+                self.escrow.to_account_info(),
+                self.owner_in.to_account_info(),
+                &self.mint_in,
+                self.order.to_account_info(),
+                self.token_program_in.to_account_info(),
+                amount_in,
+                signer_seeds,
+            )?;
+        }
+        let accounts = CloseAccount {
+            account: self.escrow.to_account_info(),
+            destination: self.owner.to_account_info(),
+            authority: self.order.to_account_info(),
+        };
+        close_account(CpiContext::new_with_signer(
+            self.token_program_in.to_account_info(),
+            accounts,
+            signer_seeds,
+        ))?;
+        self.config.open_orders = self.config.open_orders.saturating_sub(1);
+        emit!(OrderCancelled {
+            config,
+            order: self.order.key(),
+            owner,
+            caller: self.caller.key(),
+            amount_in,
+        });
+        Ok(())
+    }
+}
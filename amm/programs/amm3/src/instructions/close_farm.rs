@@ -0,0 +1,124 @@
+use crate::error::AmmError;
+use crate::events::FarmClosed;
+use crate::state::{Config, Farm};
+use crate::utils::transfer_tokens;
+use anchor_lang::prelude::*;
+use anchor_spl::token_interface::{
+    burn, close_account, Burn, CloseAccount, Mint, TokenAccount, TokenInterface,
+};
+
+#[derive(Accounts)]
+pub struct CloseFarm<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    #[account(
+        seeds= [b"config", config.seed.to_le_bytes().as_ref()],
+        bump= config.config_bump,
+    )]
+    pub config: Box<Account<'info, Config>>,
+    #[account(
+        mut,
+        seeds= [b"lp", config.key().as_ref()],
+        bump= config.lp_bump,
+        mint::token_program = token_program,
+    )]
+    pub mint_lp: Box<InterfaceAccount<'info, Mint>>,
+    #[account(
+        mint::token_program = reward_token_program,
+    )]
+    pub reward_mint: Box<InterfaceAccount<'info, Mint>>,
+    #[account(
+        mut,
+        close = authority,
+        has_one = config,
+        has_one = reward_mint,
+        seeds = [b"farm", config.key().as_ref()],
+        bump = farm.bump,
+    )]
+    pub farm: Box<Account<'info, Farm>>,
+    #[account(
+        mut,
+        associated_token::mint= mint_lp,
+        associated_token::authority= farm,
+        associated_token::token_program= token_program,
+    )]
+    pub stake_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+    #[account(
+        mut,
+        associated_token::mint= reward_mint,
+        associated_token::authority= farm,
+        associated_token::token_program= reward_token_program,
+    )]
+    pub reward_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+    #[account(
+        mut,
+        token::mint = reward_mint,
+        token::authority = authority,
+        token::token_program = reward_token_program,
+    )]
+    pub destination: Box<InterfaceAccount<'info, TokenAccount>>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub reward_token_program: Interface<'info, TokenInterface>,
+}
+
+impl<'info> CloseFarm<'info> {
+    // once every stake is closed, refunds the rewards never earned to the authority and
+    // closes the farm with its vaults, which frees the pool to close
+    pub fn close_farm(&mut self) -> Result<()> {
+        self.config.check_authority(&self.authority.key())?;
+        require!(self.farm.stakers == 0, AmmError::StakesOpen);
+        let config = self.config.key();
+        let seeds = &[&b"farm"[..], config.as_ref(), &[self.farm.bump]];
+        let signer_seeds = &[&seeds[..]];
+
+        let refunded = self.reward_vault.amount;
+        if refunded > 0 {
+            transfer_tokens(
+                self.reward_vault.to_account_info(),
+                self.destination.to_account_info(),
+                &self.reward_mint,
+                self.farm.to_account_info(),
+                self.reward_token_program.to_account_info(),
+                refunded,
+                signer_seeds,
+            )?;
+        }
+        // LP can only be sent here directly, outside any stake; it is burned to the other LPs
+        if self.stake_vault.amount > 0 {
+            let accounts = Burn {
+                mint: self.mint_lp.to_account_info(),
+                from: self.stake_vault.to_account_info(),
+                authority: self.farm.to_account_info(),
+            };
+            burn(
+                CpiContext::new_with_signer(
+                    self.token_program.to_account_info(),
+                    accounts,
+                    signer_seeds,
+                ),
+                self.stake_vault.amount,
+            )?;
+        }
+        for (vault, token_program) in [
+            (&self.stake_vault, &self.token_program),
+            (&self.reward_vault, &self.reward_token_program),
+        ] {
+            let accounts = CloseAccount {
+                account: vault.to_account_info(),
+                destination: self.authority.to_account_info(),
+                authority: self.farm.to_account_info(),
+            };
+            close_account(CpiContext::new_with_signer(
+                token_program.to_account_info(),
+                accounts,
+                signer_seeds,
+            ))?;
+        }
+        emit!(FarmClosed {
+            config,
+            farm: self.farm.key(),
+            refunded,
+        });
+        Ok(())
+    }
+}
@@ -0,0 +1,307 @@
+use crate::constants::MINIMUM_LIQUIDITY;
+use crate::error::AmmError;
+use crate::events::PoolClosed;
+use crate::state::{Config, Oracle, Pair, PoolIndex, PoolType};
+use crate::utils::transfer_tokens;
+use anchor_lang::prelude::*;
+use anchor_spl::token_interface::{
+    burn, close_account, Burn, CloseAccount, Mint, TokenAccount, TokenInterface,
+};
+
+#[derive(Accounts)]
+pub struct ClosePool<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    /// CHECK: only receives the pool's rent, pinned by `has_one` on the config
+    #[account(mut)]
+    pub initializer: UncheckedAccount<'info>,
+    #[account(
+        mint::token_program = token_program_x,
+    )]
+    pub mint_x: Box<InterfaceAccount<'info, Mint>>,
+    #[account(
+        mint::token_program = token_program_y,
+    )]
+    pub mint_y: Box<InterfaceAccount<'info, Mint>>,
+    #[account(
+        mut,
+        close = initializer,
+        has_one = mint_x,
+        has_one = mint_y,
+        has_one = initializer,
+        seeds= [b"config", config.seed.to_le_bytes().as_ref()],
+        bump= config.config_bump,
+    )]
+    pub config: Box<Account<'info, Config>>,
+    // the token program cannot close a mint, so the LP mint is left behind
+    #[account(
+        mut,
+        seeds= [b"lp", config.key().as_ref()],
+        bump= config.lp_bump,
+        mint::token_program = token_program,
+    )]
+    pub mint_lp: Box<InterfaceAccount<'info, Mint>>,
+    // the minimum liquidity is burned and the account closed with the pool
+    #[account(
+        mut,
+        seeds= [b"locked", config.key().as_ref()],
+        bump= config.locked_lp_bump,
+    )]
+    pub locked_lp: Box<InterfaceAccount<'info, TokenAccount>>,
+    #[account(
+        mut,
+        associated_token::mint= mint_x,
+        associated_token::authority= config,
+        associated_token::token_program= token_program_x,
+    )]
+    pub vault_x: Box<InterfaceAccount<'info, TokenAccount>>,
+    #[account(
+        mut,
+        associated_token::mint= mint_y,
+        associated_token::authority= config,
+        associated_token::token_program= token_program_y,
+    )]
+    pub vault_y: Box<InterfaceAccount<'info, TokenAccount>>,
+    #[account(
+        mut,
+        seeds= [b"treasury", config.key().as_ref(), mint_x.key().as_ref()],
+        bump= config.treasury_x_bump,
+    )]
+    pub treasury_x: Box<InterfaceAccount<'info, TokenAccount>>,
+    #[account(
+        mut,
+        seeds= [b"treasury", config.key().as_ref(), mint_y.key().as_ref()],
+        bump= config.treasury_y_bump,
+    )]
+    pub treasury_y: Box<InterfaceAccount<'info, TokenAccount>>,
+    #[account(
+        mut,
+        close = initializer,
+        seeds= [b"oracle", config.key().as_ref()],
+        bump= config.oracle_bump,
+    )]
+    pub oracle: Box<Account<'info, Oracle>>,
+    // frees the pair and tier for a new pool
+    #[account(
+        mut,
+        close = initializer,
+        has_one = config,
+        seeds = [b"pair", mint_x.key().as_ref(), mint_y.key().as_ref(), pair.fee.to_le_bytes().as_ref()],
+        bump = pair.bump,
+    )]
+    pub pair: Box<Account<'info, Pair>>,
+    // the index page listing the pool, which drops it
+    #[account(
+        mut,
+        seeds = [b"pool_index", pool_index.page.to_le_bytes().as_ref()],
+        bump = pool_index.bump,
+    )]
+    pub pool_index: Box<Account<'info, PoolIndex>>,
+    // stake and rewards are settled through the config, so a pool with a farm stays open
+    // until `close_farm`
+    /// CHECK: only checked to hold no farm, pinned by its seeds
+    #[account(
+        seeds = [b"farm", config.key().as_ref()],
+        bump,
+    )]
+    pub farm: UncheckedAccount<'info>,
+    #[account(
+        mut,
+        associated_token::mint = mint_x,
+        associated_token::authority = authority,
+        associated_token::token_program = token_program_x,
+    )]
+    pub destination_x: Box<InterfaceAccount<'info, TokenAccount>>,
+    #[account(
+        mut,
+        associated_token::mint = mint_y,
+        associated_token::authority = authority,
+        associated_token::token_program = token_program_y,
+    )]
+    pub destination_y: Box<InterfaceAccount<'info, TokenAccount>>,
+    // forced exits only: hold the vaults for the LPs that did not withdraw in time, and pay
+    // them out on `redeem`. Each is its own authority, like the locked LP account
+    #[account(
+        init,
+        payer = authority,
+        seeds = [b"exit", config.key().as_ref(), mint_x.key().as_ref()],
+        bump,
+        token::mint = mint_x,
+        token::authority = exit_x,
+        token::token_program = token_program_x,
+    )]
+    pub exit_x: Option<Box<InterfaceAccount<'info, TokenAccount>>>,
+    #[account(
+        init,
+        payer = authority,
+        seeds = [b"exit", config.key().as_ref(), mint_y.key().as_ref()],
+        bump,
+        token::mint = mint_y,
+        token::authority = exit_y,
+        token::token_program = token_program_y,
+    )]
+    pub exit_y: Option<Box<InterfaceAccount<'info, TokenAccount>>>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub token_program_x: Interface<'info, TokenInterface>,
+    pub token_program_y: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+}
+
+impl<'info> ClosePool<'info> {
+    // open to the authority once every LP has withdrawn, or a forced exit is due, with no
+    // open orders or farm left on the pool. The treasuries go to the authority, and so do the
+    // vaults unless LPs are left, in which case they move to the exit accounts; all rent
+    // goes to the initializer
+    pub fn close_pool(&mut self) -> Result<()> {
+        self.config.check_authority(&self.authority.key())?;
+        require!(
+            self.config.flash_loan_amount == 0,
+            AmmError::FlashLoanActive
+        );
+        require!(self.config.open_orders == 0, AmmError::OrdersOpen);
+        require!(self.farm.data_is_empty(), AmmError::FarmActive);
+        // a graduated launch locks all of its LP, and with it the whole pool
+        require!(
+            self.locked_lp.amount <= MINIMUM_LIQUIDITY,
+            AmmError::LiquidityLocked
+        );
+        // only the locked minimum liquidity is left, so the vaults back no one's LP, or the
+        // LPs left after a forced exit's notice keep their claim through the exit accounts.
+        // Before it graduates a launch has no LP at all, and its vaults back the buyers' exits
+        let outstanding = self.mint_lp.supply > self.locked_lp.amount;
+        let forced = outstanding && self.config.forced_exit_due(Clock::get()?.unix_timestamp);
+        let drained = (!outstanding || forced)
+            && (self.config.pool_type != PoolType::Launch
+                || (self.vault_x.amount == 0 && self.vault_y.amount == 0));
+        require!(drained, AmmError::PoolNotDrained);
+        require!(
+            self.exit_x.is_some() == forced && self.exit_y.is_some() == forced,
+            AmmError::ExitAccountMismatch
+        );
+        self.burn_locked_lp()?;
+        let position = self
+            .pool_index
+            .pools
+            .iter()
+            .position(|pool| *pool == self.config.key())
+            .ok_or(AmmError::PoolNotIndexed)?;
+        self.pool_index.pools.remove(position);
+
+        let (vault_x, vault_y) = (
+            self.empty_and_close(true, true)?,
+            self.empty_and_close(false, true)?,
+        );
+        let (treasury_x, treasury_y) = (
+            self.empty_and_close(true, false)?,
+            self.empty_and_close(false, false)?,
+        );
+        let (amount_x, amount_y, exit_x, exit_y) = match forced {
+            true => (treasury_x, treasury_y, vault_x, vault_y),
+            false => (vault_x + treasury_x, vault_y + treasury_y, 0, 0),
+        };
+        emit!(PoolClosed {
+            config: self.config.key(),
+            amount_x,
+            amount_y,
+            exit_x,
+            exit_y,
+        });
+        Ok(())
+    }
+
+    pub fn burn_locked_lp(&self) -> Result<()> {
+        let config = self.config.key();
+        let seeds = &[
+            &b"locked"[..],
+            config.as_ref(),
+            &[self.config.locked_lp_bump],
+        ];
+        let signer_seeds = &[&seeds[..]];
+        let amount = self.locked_lp.amount;
+        if amount > 0 {
+            let accounts = Burn {
+                mint: self.mint_lp.to_account_info(),
+                from: self.locked_lp.to_account_info(),
+                authority: self.locked_lp.to_account_info(),
+            };
+            burn(
+                CpiContext::new_with_signer(
+                    self.token_program.to_account_info(),
+                    accounts,
+                    signer_seeds,
+                ),
+                amount,
+            )?;
+        }
+        let accounts = CloseAccount {
+            account: self.locked_lp.to_account_info(),
+            destination: self.initializer.to_account_info(),
+            authority: self.locked_lp.to_account_info(),
+        };
+        close_account(CpiContext::new_with_signer(
+            self.token_program.to_account_info(),
+            accounts,
+            signer_seeds,
+        ))
+    }
+
+    // sweeps a vault (or treasury) to the destination, or a vault to its exit account when
+    // there is one, and closes it; returns what it held
+    pub fn empty_and_close(&self, is_x: bool, vault: bool) -> Result<u64> {
+        let (account, destination, exit, mint, token_program) = match is_x {
+            true => (
+                match vault {
+                    true => self.vault_x.as_ref(),
+                    false => self.treasury_x.as_ref(),
+                },
+                self.destination_x.to_account_info(),
+                self.exit_x.as_ref(),
+                &self.mint_x,
+                self.token_program_x.to_account_info(),
+            ),
+            false => (
+                match vault {
+                    true => self.vault_y.as_ref(),
+                    false => self.treasury_y.as_ref(),
+                },
+                self.destination_y.to_account_info(),
+                self.exit_y.as_ref(),
+                &self.mint_y,
+                self.token_program_y.to_account_info(),
+            ),
+        };
+        let destination = match (vault, exit) {
+            (true, Some(exit)) => exit.to_account_info(),
+            _ => destination,
+        };
+        let seeds = &[
+            &b"config"[..],
+            &self.config.seed.to_le_bytes(),
+            &[self.config.config_bump],
+        ];
+        let signer_seeds = &[&seeds[..]];
+        let amount = account.amount;
+        if amount > 0 {
+            transfer_tokens(
+                account.to_account_info(),
+                destination,
+                mint,
+                self.config.to_account_info(),
+                token_program.clone(),
+                amount,
+                signer_seeds,
+            )?;
+        }
+        let accounts = CloseAccount {
+            account: account.to_account_info(),
+            destination: self.initializer.to_account_info(),
+            authority: self.config.to_account_info(),
+        };
+        close_account(CpiContext::new_with_signer(
+            token_program,
+            accounts,
+            signer_seeds,
+        ))?;
+        Ok(amount)
+    }
+}
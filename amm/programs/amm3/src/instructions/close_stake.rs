@@ -0,0 +1,150 @@
+use crate::error::AmmError;
+use crate::events::StakeClosed;
+use crate::state::{Config, Farm, UserStake};
+use crate::utils::transfer_tokens;
+use anchor_lang::prelude::*;
+use anchor_spl::{
+    associated_token::AssociatedToken,
+    token_interface::{Mint, TokenAccount, TokenInterface},
+};
+
+#[derive(Accounts)]
+pub struct CloseStake<'info> {
+    #[account(mut)]
+    pub caller: Signer<'info>,
+    /// CHECK: only receives the stake's LP, rewards and rent, pinned by `has_one` on the stake
+    #[account(mut)]
+    pub owner: UncheckedAccount<'info>,
+    #[account(
+        seeds= [b"config", config.seed.to_le_bytes().as_ref()],
+        bump= config.config_bump,
+    )]
+    pub config: Box<Account<'info, Config>>,
+    #[account(
+        seeds= [b"lp", config.key().as_ref()],
+        bump= config.lp_bump,
+        mint::token_program = token_program,
+    )]
+    pub mint_lp: Box<InterfaceAccount<'info, Mint>>,
+    #[account(
+        mint::token_program = reward_token_program,
+    )]
+    pub reward_mint: Box<InterfaceAccount<'info, Mint>>,
+    #[account(
+        mut,
+        has_one = config,
+        has_one = reward_mint,
+        seeds = [b"farm", config.key().as_ref()],
+        bump = farm.bump,
+    )]
+    pub farm: Box<Account<'info, Farm>>,
+    #[account(
+        mut,
+        close = owner,
+        has_one = farm,
+        has_one = owner,
+        seeds = [b"stake", farm.key().as_ref(), owner.key().as_ref()],
+        bump = user_stake.bump,
+    )]
+    pub user_stake: Box<Account<'info, UserStake>>,
+    #[account(
+        mut,
+        associated_token::mint= mint_lp,
+        associated_token::authority= farm,
+        associated_token::token_program= token_program,
+    )]
+    pub stake_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+    #[account(
+        mut,
+        associated_token::mint= reward_mint,
+        associated_token::authority= farm,
+        associated_token::token_program= reward_token_program,
+    )]
+    pub reward_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+    #[account(
+        init_if_needed,
+        payer = caller,
+        associated_token::mint= mint_lp,
+        associated_token::authority= owner,
+        associated_token::token_program= token_program,
+    )]
+    pub owner_lp: Box<InterfaceAccount<'info, TokenAccount>>,
+    #[account(
+        init_if_needed,
+        payer = caller,
+        associated_token::mint= reward_mint,
+        associated_token::authority= owner,
+        associated_token::token_program= reward_token_program,
+    )]
+    pub owner_reward: Box<InterfaceAccount<'info, TokenAccount>>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub reward_token_program: Interface<'info, TokenInterface>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+}
+
+impl<'info> CloseStake<'info> {
+    // pays out the whole stake and everything it earned, then closes it. Open to the owner at
+    // any time, and to anyone once a forced exit is due, so stakers cannot hold the pool open
+    pub fn close_stake(&mut self) -> Result<()> {
+        let now = Clock::get()?.unix_timestamp;
+        require!(
+            self.caller.key() == self.owner.key() || self.config.forced_exit_due(now),
+            AmmError::ForcedExitNotDue
+        );
+        // as on unstake, a farm that cannot accrue settles at the last accrued rate
+        let _ = self.farm.accrue(now);
+        self.user_stake.settle(self.farm.reward_per_share);
+
+        let amount = self.user_stake.amount;
+        if amount > 0 {
+            self.withdraw_from_farm(false, amount)?;
+        }
+        // rounding never lets stakes earn more than was funded, the cap only keeps a shortfall
+        // from trapping the stake
+        let rewards = self.user_stake.pending.min(self.reward_vault.amount);
+        if rewards > 0 {
+            self.withdraw_from_farm(true, rewards)?;
+        }
+        self.farm.total_staked -= amount;
+        self.farm.stakers = self.farm.stakers.saturating_sub(1);
+        emit!(StakeClosed {
+            config: self.config.key(),
+            farm: self.farm.key(),
+            owner: self.owner.key(),
+            amount,
+            rewards,
+        });
+        Ok(())
+    }
+
+    // pays rewards, or returns staked LP, out of the farm's vaults
+    pub fn withdraw_from_farm(&self, is_reward: bool, amount: u64) -> Result<()> {
+        let (from, to, mint, token_program) = match is_reward {
+            true => (
+                self.reward_vault.to_account_info(),
+                self.owner_reward.to_account_info(),
+                &self.reward_mint,
+                self.reward_token_program.to_account_info(),
+            ),
+            false => (
+                self.stake_vault.to_account_info(),
+                self.owner_lp.to_account_info(),
+                &self.mint_lp,
+                self.token_program.to_account_info(),
+            ),
+        };
+        let config = self.config.key();
+        let seeds = &[&b"farm"[..], config.as_ref(), &[self.farm.bump]];
+        let signer_seeds = &[&seeds[..]];
+        transfer_tokens(
+            from,
+            to,
+            mint,
+            self.farm.to_account_info(),
+            token_program,
+            amount,
+            signer_seeds,
+        )
+    }
+}
@@ -0,0 +1,102 @@
+use crate::events::ProtocolFeesCollected;
+use crate::state::Config;
+use crate::utils::transfer_tokens;
+use anchor_lang::prelude::*;
+use anchor_spl::token_interface::{Mint, TokenAccount, TokenInterface};
+
+#[derive(Accounts)]
+pub struct CollectProtocolFees<'info> {
+    pub authority: Signer<'info>,
+    #[account(
+        mint::token_program = token_program_x,
+    )]
+    pub mint_x: Box<InterfaceAccount<'info, Mint>>,
+    #[account(
+        mint::token_program = token_program_y,
+    )]
+    pub mint_y: Box<InterfaceAccount<'info, Mint>>,
+    #[account(
+        has_one = mint_x,
+        has_one = mint_y,
+        seeds= [b"config", config.seed.to_le_bytes().as_ref()],
+        bump= config.config_bump,
+    )]
+    pub config: Box<Account<'info, Config>>,
+    #[account(
+        mut,
+        seeds= [b"treasury", config.key().as_ref(), mint_x.key().as_ref()],
+        bump= config.treasury_x_bump,
+    )]
+    pub treasury_x: Box<InterfaceAccount<'info, TokenAccount>>,
+    #[account(
+        mut,
+        seeds= [b"treasury", config.key().as_ref(), mint_y.key().as_ref()],
+        bump= config.treasury_y_bump,
+    )]
+    pub treasury_y: Box<InterfaceAccount<'info, TokenAccount>>,
+    #[account(
+        mut,
+        token::mint = mint_x,
+        token::token_program = token_program_x,
+    )]
+    pub destination_x: Box<InterfaceAccount<'info, TokenAccount>>,
+    #[account(
+        mut,
+        token::mint = mint_y,
+        token::token_program = token_program_y,
+    )]
+    pub destination_y: Box<InterfaceAccount<'info, TokenAccount>>,
+    pub token_program_x: Interface<'info, TokenInterface>,
+    pub token_program_y: Interface<'info, TokenInterface>,
+}
+
+impl<'info> CollectProtocolFees<'info> {
+    pub fn collect_protocol_fees(&mut self) -> Result<()> {
+        self.config.check_authority(&self.authority.key())?;
+        let amount_x = self.treasury_x.amount;
+        let amount_y = self.treasury_y.amount;
+        self.withdraw_treasury(true, amount_x)?;
+        self.withdraw_treasury(false, amount_y)?;
+        emit!(ProtocolFeesCollected {
+            config: self.config.key(),
+            amount_x,
+            amount_y,
+        });
+        Ok(())
+    }
+
+    pub fn withdraw_treasury(&self, is_x: bool, amount: u64) -> Result<()> {
+        if amount == 0 {
+            return Ok(());
+        }
+        let (from, to, mint, token_program) = match is_x {
+            true => (
+                self.treasury_x.to_account_info(),
+                self.destination_x.to_account_info(),
+                &self.mint_x,
+                self.token_program_x.to_account_info(),
+            ),
+            false => (
+                self.treasury_y.to_account_info(),
+                self.destination_y.to_account_info(),
+                &self.mint_y,
+                self.token_program_y.to_account_info(),
+            ),
+        };
+        let seeds = &[
+            &b"config"[..],
+            &self.config.seed.to_le_bytes(),
+            &[self.config.config_bump],
+        ];
+        let signer_seeds = &[&seeds[..]];
+        transfer_tokens(
+            from,
+            to,
+            mint,
+            self.config.to_account_info(),
+            token_program,
+            amount,
+            signer_seeds,
+        )
+    }
+}
@@ -0,0 +1,77 @@
+use crate::state::{Config, Farm};
+use crate::utils::check_mint_extensions;
+use anchor_lang::prelude::*;
+use anchor_spl::{
+    associated_token::AssociatedToken,
+    token_interface::{Mint, TokenAccount, TokenInterface},
+};
+
+#[derive(Accounts)]
+pub struct CreateFarm<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    #[account(
+        seeds= [b"config", config.seed.to_le_bytes().as_ref()],
+        bump= config.config_bump,
+    )]
+    pub config: Box<Account<'info, Config>>,
+    #[account(
+        seeds= [b"lp", config.key().as_ref()],
+        bump= config.lp_bump,
+        mint::token_program = token_program,
+    )]
+    pub mint_lp: Box<InterfaceAccount<'info, Mint>>,
+    #[account(
+        mint::token_program = reward_token_program,
+    )]
+    pub reward_mint: Box<InterfaceAccount<'info, Mint>>,
+    #[account(
+        init,
+        payer = authority,
+        seeds = [b"farm", config.key().as_ref()],
+        bump,
+        space = 8 + Farm::INIT_SPACE,
+    )]
+    pub farm: Box<Account<'info, Farm>>,
+    #[account(
+        init,
+        payer = authority,
+        associated_token::mint= mint_lp,
+        associated_token::authority= farm,
+        associated_token::token_program= token_program,
+    )]
+    pub stake_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+    #[account(
+        init,
+        payer = authority,
+        associated_token::mint= reward_mint,
+        associated_token::authority= farm,
+        associated_token::token_program= reward_token_program,
+    )]
+    pub reward_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub reward_token_program: Interface<'info, TokenInterface>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+}
+
+impl<'info> CreateFarm<'info> {
+    // the farm starts empty, emissions begin once the authority funds it
+    pub fn create_farm(&mut self, bumps: CreateFarmBumps) -> Result<()> {
+        self.config.check_authority(&self.authority.key())?;
+        check_mint_extensions(&self.reward_mint.to_account_info())?;
+        let now = Clock::get()?.unix_timestamp;
+        self.farm.set_inner(Farm {
+            config: self.config.key(),
+            reward_mint: self.reward_mint.key(),
+            emission_rate: 0,
+            end_time: now,
+            last_update: now,
+            reward_per_share: 0,
+            total_staked: 0,
+            stakers: 0,
+            bump: bumps.farm,
+        });
+        Ok(())
+    }
+}
@@ -1,120 +1,271 @@
+use crate::constants::MINIMUM_LIQUIDITY;
+use crate::curve::{deposit_amounts, initial_liquidity};
 use crate::error::AmmError;
-use crate::state::Config;
+use crate::events::LiquidityAdded;
+use crate::state::{AllowlistEntry, Config, Oracle, PoolType};
+use crate::utils::{
+    check_expiry, inverse_transfer_fee, is_native_mint, transfer_fee, transfer_tokens,
+    unwrap_native, wrap_native,
+};
 use anchor_lang::prelude::*;
 use anchor_spl::{
     associated_token::AssociatedToken,
-    token::{mint_to, transfer, Mint, MintTo, Token, TokenAccount, Transfer},
+    token_interface::{mint_to, Mint, MintTo, TokenAccount, TokenInterface},
 };
-use constant_product_curve::ConstantProduct;
 
 #[derive(Accounts)]
 pub struct Deposit<'info> {
     #[account(mut)]
     pub user: Signer<'info>,
-    pub mint_x: Account<'info, Mint>,
-    pub mint_y: Account<'info, Mint>,
+    #[account(
+        mint::token_program = token_program_x,
+    )]
+    pub mint_x: Box<InterfaceAccount<'info, Mint>>,
+    #[account(
+        mint::token_program = token_program_y,
+    )]
+    pub mint_y: Box<InterfaceAccount<'info, Mint>>,
     #[account(
         has_one = mint_x,
         has_one = mint_y,
         seeds= [b"config", config.seed.to_le_bytes().as_ref()],
         bump=config.config_bump,
     )]
-    pub config: Account<'info, Config>,
+    pub config: Box<Account<'info, Config>>,
     #[account(
         mut,
         seeds=[b"lp", config.key().as_ref()],
         bump=config.lp_bump,
+        mint::token_program = token_program,
     )]
-    pub mint_lp: Account<'info, Mint>,
+    pub mint_lp: Box<InterfaceAccount<'info, Mint>>,
+    #[account(
+        mut,
+        seeds=[b"locked", config.key().as_ref()],
+        bump=config.locked_lp_bump,
+    )]
+    pub locked_lp: Box<InterfaceAccount<'info, TokenAccount>>,
     #[account(
         mut,
         associated_token::mint= mint_x,
         associated_token::authority= config,
+        associated_token::token_program= token_program_x,
     )]
-    pub vault_x: Account<'info, TokenAccount>,
+    pub vault_x: Box<InterfaceAccount<'info, TokenAccount>>,
     #[account(
         mut,
         associated_token::mint= mint_y,
         associated_token::authority= config,
+        associated_token::token_program= token_program_y,
     )]
-    pub vault_y: Account<'info, TokenAccount>,
+    pub vault_y: Box<InterfaceAccount<'info, TokenAccount>>,
+    // left out for the wSOL side in native SOL mode
     #[account(
-        mut,
+        init_if_needed,
+        payer = user,
         associated_token::mint= mint_x,
         associated_token::authority= user,
+        associated_token::token_program= token_program_x,
     )]
-    pub user_x: Account<'info, TokenAccount>,
+    pub user_x: Option<Box<InterfaceAccount<'info, TokenAccount>>>,
     #[account(
-        mut,
+        init_if_needed,
+        payer = user,
         associated_token::mint= mint_y,
         associated_token::authority= user,
+        associated_token::token_program= token_program_y,
     )]
-    pub user_y: Account<'info, TokenAccount>,
+    pub user_y: Option<Box<InterfaceAccount<'info, TokenAccount>>>,
     #[account(
         init_if_needed,
         payer = user,
         associated_token::mint= mint_lp,
         associated_token::authority= user,
+        associated_token::token_program= token_program,
     )]
-    pub user_lp: Account<'info, TokenAccount>,
-
-    pub token_program: Program<'info, Token>,
+    pub user_lp: Box<InterfaceAccount<'info, TokenAccount>>,
+    #[account(
+        mut,
+        seeds= [b"oracle", config.key().as_ref()],
+        bump= config.oracle_bump,
+    )]
+    pub oracle: Box<Account<'info, Oracle>>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub token_program_x: Interface<'info, TokenInterface>,
+    pub token_program_y: Interface<'info, TokenInterface>,
     pub system_program: Program<'info, System>,
     pub associated_token_program: Program<'info, AssociatedToken>,
+    // the user's entry, needed only while the pool is in allowlist mode
+    pub allowlist_entry: Option<Box<Account<'info, AllowlistEntry>>>,
+    // native SOL mode only: stands in for the user's wSOL account within the instruction
+    /// CHECK: created by `wrap_native` and closed by `unwrap_native`, pinned by its seeds
+    #[account(
+        mut,
+        seeds = [b"wsol", user.key().as_ref()],
+        bump,
+    )]
+    pub native_account: Option<UncheckedAccount<'info>>,
 }
 
 impl<'info> Deposit<'info> {
-    pub fn deposit(&mut self, amount: u64, max_x: u64, max_y: u64) -> Result<()> {
-        require!(self.config.locked == false, AmmError::PoolLocked);
+    pub fn deposit(
+        &mut self,
+        amount: u64,
+        max_x: u64,
+        max_y: u64,
+        expires_at: i64,
+        native_sol: bool,
+    ) -> Result<()> {
+        let now = check_expiry(expires_at)?;
+        self.config.check_unlocked()?;
+        self.config.check_not_closing()?;
+        self.config.check_allowlisted(
+            &self.config.key(),
+            &self.user.key(),
+            self.allowlist_entry.as_deref(),
+        )?;
         require!(amount != 0, AmmError::InvalidAmount);
-        let (x, y) = match self.mint_lp.supply == 0
-            && self.vault_x.amount == 0
-            && self.vault_y.amount == 0
-        {
-            true => (max_x, max_y),
-            false => {
-                let amounts = ConstantProduct::xy_deposit_amounts_from_l(
-                    self.vault_x.amount,
-                    self.vault_y.amount,
-                    self.mint_lp.supply,
-                    amount,
-                    6,
-                )
-                .unwrap();
-                (amounts.x, amounts.y)
-            }
-        };
-        require!(x <= max_x && y <= max_y, AmmError::SlippageExceded);
-        self.deposit_tokens(true, x)?;
-        self.deposit_tokens(false, y)?;
+        // a launch only gets liquidity when it graduates
+        require!(
+            self.config.pool_type != PoolType::Launch,
+            AmmError::InvalidPoolType
+        );
+        require!(
+            native_sol == self.native_account.is_some(),
+            AmmError::NativeAccountMismatch
+        );
+        if native_sol {
+            self.config.check_native()?;
+        }
+        let supply = self.mint_lp.supply;
+        if supply == 0 {
+            self.deposit_initial(amount, max_x, max_y)?;
+        } else {
+            let (x, y) =
+                deposit_amounts(self.vault_x.amount, self.vault_y.amount, supply, amount)?;
+            // the vaults have to receive those amounts after any transfer fee
+            let x = x
+                .checked_add(inverse_transfer_fee(&self.mint_x.to_account_info(), x)?)
+                .ok_or(AmmError::Overflow)?;
+            let y = y
+                .checked_add(inverse_transfer_fee(&self.mint_y.to_account_info(), y)?)
+                .ok_or(AmmError::Overflow)?;
+            require!(x <= max_x && y <= max_y, AmmError::SlippageExceded);
+            self.deposit_tokens(true, x)?;
+            self.deposit_tokens(false, y)?;
+
+            self.mint_lp_tokens(self.user_lp.to_account_info(), amount)?;
+            self.emit_liquidity_added(x, y, amount)?;
+        }
+        self.oracle.update(&self.config, now, self.vault_x.amount, self.vault_y.amount);
+        // the temporary wSOL account goes back to the user as lamports
+        if let Some(native_account) = self.native_account.as_ref() {
+            let token_program = match is_native_mint(&self.mint_x.key()) {
+                true => self.token_program_x.to_account_info(),
+                false => self.token_program_y.to_account_info(),
+            };
+            unwrap_native(
+                native_account.to_account_info(),
+                self.user.to_account_info(),
+                token_program,
+            )?;
+        }
+        Ok(())
+    }
+    // the first deposit sets the price with all of `max_x` and `max_y`; LP is the geometric
+    // mean of what the vaults receive, `amount` is only the least the depositor accepts
+    pub fn deposit_initial(&mut self, amount: u64, max_x: u64, max_y: u64) -> Result<()> {
+        let x = max_x
+            .checked_sub(transfer_fee(&self.mint_x.to_account_info(), max_x)?)
+            .ok_or(AmmError::Underflow)?;
+        let y = max_y
+            .checked_sub(transfer_fee(&self.mint_y.to_account_info(), max_y)?)
+            .ok_or(AmmError::Underflow)?;
+        let liquidity = initial_liquidity(
+            x,
+            y,
+            self.config.decimals_x,
+            self.config.decimals_y,
+            self.config.lp_decimals,
+        )?;
+        require!(
+            liquidity > MINIMUM_LIQUIDITY,
+            AmmError::LiquidityLessThanMinium
+        );
+        let minted = liquidity - MINIMUM_LIQUIDITY;
+        require!(minted >= amount, AmmError::SlippageExceded);
+        self.deposit_tokens(true, max_x)?;
+        self.deposit_tokens(false, max_y)?;
 
-        self.mint_lp_tokens(amount)?;
+        self.mint_lp_tokens(self.locked_lp.to_account_info(), MINIMUM_LIQUIDITY)?;
+        self.mint_lp_tokens(self.user_lp.to_account_info(), minted)?;
+        self.emit_liquidity_added(max_x, max_y, minted)
+    }
+    pub fn emit_liquidity_added(
+        &mut self,
+        amount_x: u64,
+        amount_y: u64,
+        lp_amount: u64,
+    ) -> Result<()> {
+        self.vault_x.reload()?;
+        self.vault_y.reload()?;
+        emit!(LiquidityAdded {
+            config: self.config.key(),
+            user: self.user.key(),
+            amount_x,
+            amount_y,
+            lp_amount,
+            reserve_x: self.vault_x.amount,
+            reserve_y: self.vault_y.amount,
+        });
         Ok(())
     }
+    // native SOL mode pays the wSOL side from the temporary account
     pub fn deposit_tokens(&self, is_x: bool, amount: u64) -> Result<()> {
-        let (from, to) = match is_x {
+        let (from, to, mint, token_program) = match is_x {
             true => (
-                self.user_x.to_account_info(),
+                self.user_x.as_ref(),
                 self.vault_x.to_account_info(),
+                &self.mint_x,
+                self.token_program_x.to_account_info(),
             ),
             false => (
-                self.user_y.to_account_info(),
+                self.user_y.as_ref(),
                 self.vault_y.to_account_info(),
+                &self.mint_y,
+                self.token_program_y.to_account_info(),
             ),
         };
-        let cpi_accounts = Transfer {
+        let from = match self.native_account.as_ref() {
+            Some(native_account) if is_native_mint(&mint.key()) => {
+                wrap_native(
+                    mint,
+                    native_account.to_account_info(),
+                    self.user.to_account_info(),
+                    self.system_program.to_account_info(),
+                    token_program.clone(),
+                    amount,
+                )?;
+                native_account.to_account_info()
+            }
+            _ => from
+                .ok_or(AmmError::MissingTokenAccount)?
+                .to_account_info(),
+        };
+        transfer_tokens(
             from,
             to,
-            authority: self.user.to_account_info(),
-        };
-        let ctx = CpiContext::new(self.token_program.to_account_info(), cpi_accounts);
-        transfer(ctx, amount)?;
-        Ok(())
+            mint,
+            self.user.to_account_info(),
+            token_program,
+            amount,
+            &[],
+        )
     }
-    pub fn mint_lp_tokens(&mut self, amount: u64) -> Result<()> {
+    pub fn mint_lp_tokens(&self, to: AccountInfo<'info>, amount: u64) -> Result<()> {
         let cpi_accounts = MintTo {
             mint: self.mint_lp.to_account_info(),
-            to: self.user_lp.to_account_info(),
+            to,
             authority: self.config.to_account_info(),
         };
         let seeds = &[
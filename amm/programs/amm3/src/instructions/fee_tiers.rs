@@ -0,0 +1,81 @@
+use crate::constants::MAX_BPS;
+use crate::error::AmmError;
+use crate::events::{FeeTierAdded, FeeTierRemoved};
+use crate::program::Amm3;
+use crate::state::{FeeTiers, MAX_FEE_TIERS};
+use anchor_lang::prelude::*;
+
+// only the program's upgrade authority may create the table and name its governance authority
+#[derive(Accounts)]
+pub struct InitFeeTiers<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(
+        init,
+        payer = payer,
+        seeds = [b"fee_tiers"],
+        bump,
+        space = 8 + FeeTiers::INIT_SPACE,
+    )]
+    pub fee_tiers: Box<Account<'info, FeeTiers>>,
+    #[account(constraint = program.programdata_address()? == Some(program_data.key()))]
+    pub program: Program<'info, Amm3>,
+    #[account(
+        constraint = program_data.upgrade_authority_address == Some(payer.key())
+            @ AmmError::InvalidAuthority,
+    )]
+    pub program_data: Account<'info, ProgramData>,
+    pub system_program: Program<'info, System>,
+}
+
+impl<'info> InitFeeTiers<'info> {
+    pub fn init_fee_tiers(&mut self, authority: Pubkey, bumps: InitFeeTiersBumps) -> Result<()> {
+        self.fee_tiers.set_inner(FeeTiers {
+            authority,
+            bump: bumps.fee_tiers,
+            fees: Vec::new(),
+        });
+        Ok(())
+    }
+}
+
+#[derive(Accounts)]
+pub struct UpdateFeeTiers<'info> {
+    pub authority: Signer<'info>,
+    #[account(
+        mut,
+        has_one = authority @ AmmError::InvalidAuthority,
+        seeds = [b"fee_tiers"],
+        bump = fee_tiers.bump,
+    )]
+    pub fee_tiers: Box<Account<'info, FeeTiers>>,
+}
+
+impl<'info> UpdateFeeTiers<'info> {
+    pub fn add_fee_tier(&mut self, fee: u16) -> Result<()> {
+        require!(
+            fee < MAX_BPS && !self.fee_tiers.fees.contains(&fee),
+            AmmError::InvalidFee
+        );
+        require!(
+            self.fee_tiers.fees.len() < MAX_FEE_TIERS,
+            AmmError::FeeTiersFull
+        );
+        self.fee_tiers.fees.push(fee);
+        emit!(FeeTierAdded { fee });
+        Ok(())
+    }
+
+    // pools already created at the tier keep their fee
+    pub fn remove_fee_tier(&mut self, fee: u16) -> Result<()> {
+        let index = self
+            .fee_tiers
+            .fees
+            .iter()
+            .position(|tier| *tier == fee)
+            .ok_or(AmmError::InvalidFee)?;
+        self.fee_tiers.fees.swap_remove(index);
+        emit!(FeeTierRemoved { fee });
+        Ok(())
+    }
+}
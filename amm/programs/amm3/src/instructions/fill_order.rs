@@ -0,0 +1,234 @@
+use crate::error::AmmError;
+use crate::events::{OrderFilled, Swapped};
+use crate::state::{Config, Oracle, Order};
+use crate::utils::{transfer_fee, transfer_tokens};
+use anchor_lang::prelude::*;
+use anchor_spl::{
+    associated_token::AssociatedToken,
+    token_interface::{close_account, CloseAccount, Mint, TokenAccount, TokenInterface},
+};
+
+#[derive(Accounts)]
+pub struct FillOrder<'info> {
+    #[account(mut)]
+    pub keeper: Signer<'info>,
+    /// CHECK: only receives the order's rent and escrowed output, pinned by `has_one` on the order
+    #[account(mut)]
+    pub owner: UncheckedAccount<'info>,
+    #[account(
+        address = order.mint_in,
+        mint::token_program = token_program_in,
+    )]
+    pub mint_in: Box<InterfaceAccount<'info, Mint>>,
+    #[account(
+        address = order.mint_out,
+        mint::token_program = token_program_out,
+    )]
+    pub mint_out: Box<InterfaceAccount<'info, Mint>>,
+    #[account(
+        mut,
+        seeds= [b"config", config.seed.to_le_bytes().as_ref()],
+        bump= config.config_bump,
+    )]
+    pub config: Box<Account<'info, Config>>,
+    #[account(
+        mut,
+        close = owner,
+        has_one = config,
+        has_one = owner,
+        seeds = [b"order", config.key().as_ref(), owner.key().as_ref(), order.id.to_le_bytes().as_ref()],
+        bump = order.bump,
+    )]
+    pub order: Box<Account<'info, Order>>,
+    #[account(
+        mut,
+        associated_token::mint= mint_in,
+        associated_token::authority= order,
+        associated_token::token_program= token_program_in,
+    )]
+    pub escrow: Box<InterfaceAccount<'info, TokenAccount>>,
+    #[account(
+        mut,
+        associated_token::mint= mint_in,
+        associated_token::authority= config,
+        associated_token::token_program= token_program_in,
+    )]
+    pub vault_in: Box<InterfaceAccount<'info, TokenAccount>>,
+    #[account(
+        mut,
+        associated_token::mint= mint_out,
+        associated_token::authority= config,
+        associated_token::token_program= token_program_out,
+    )]
+    pub vault_out: Box<InterfaceAccount<'info, TokenAccount>>,
+    #[account(
+        mut,
+        seeds= [b"treasury", config.key().as_ref(), mint_in.key().as_ref()],
+        bump= match order.is_x {
+            true => config.treasury_x_bump,
+            false => config.treasury_y_bump,
+        },
+    )]
+    pub treasury_in: Box<InterfaceAccount<'info, TokenAccount>>,
+    #[account(
+        init_if_needed,
+        payer = keeper,
+        associated_token::mint= mint_out,
+        associated_token::authority= owner,
+        associated_token::token_program= token_program_out,
+    )]
+    pub owner_out: Box<InterfaceAccount<'info, TokenAccount>>,
+    #[account(
+        mut,
+        seeds= [b"oracle", config.key().as_ref()],
+        bump= config.oracle_bump,
+    )]
+    pub oracle: Box<Account<'info, Oracle>>,
+    pub token_program_in: Interface<'info, TokenInterface>,
+    pub token_program_out: Interface<'info, TokenInterface>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+}
+
+impl<'info> FillOrder<'info> {
+    // any keeper may fill once swapping the whole order through the pool pays the owner at
+    // least the limit; the keeper takes the tip, the owner gets the rent back
+    pub fn fill_order(&mut self) -> Result<()> {
+        let now = Clock::get()?.unix_timestamp;
+        require!(now <= self.order.expires_at, AmmError::OrderExpired);
+        self.config.check_unlocked()?;
+        require!(!self.config.allowlist, AmmError::NotAllowlisted);
+        self.config.refresh_dynamic_fee(Clock::get()?.slot);
+        let is_x = self.order.is_x;
+        let (reserve_x, reserve_y) = match is_x {
+            true => (self.vault_in.amount, self.vault_out.amount),
+            false => (self.vault_out.amount, self.vault_in.amount),
+        };
+        // anything sent to the escrow on top of the order is sold with it
+        let amount_in = self.escrow.amount;
+        let mint_in = self.mint_in.to_account_info();
+        let received = amount_in
+            .checked_sub(transfer_fee(&mint_in, amount_in)?)
+            .ok_or(AmmError::Underflow)?;
+        let res = self.config.quote_exact_in(reserve_x, reserve_y, is_x, received, now)?;
+        let mint_out = self.mint_out.to_account_info();
+        let amount_out = res
+            .withdraw
+            .checked_sub(transfer_fee(&mint_out, res.withdraw)?)
+            .ok_or(AmmError::Underflow)?;
+        require!(amount_out >= self.order.min_out, AmmError::LimitNotReached);
+
+        self.release_escrow(amount_in)?;
+        let protocol_fee = self.config.protocol_fee_amount(res.fee)?;
+        if protocol_fee > 0 {
+            self.withdraw_from_vault(true, protocol_fee)?;
+        }
+        self.withdraw_from_vault(false, res.withdraw)?;
+
+        let tip = self.order.tip;
+        if tip > 0 {
+            self.order.sub_lamports(tip)?;
+            self.keeper.add_lamports(tip)?;
+        }
+
+        let before = (reserve_x, reserve_y);
+        self.vault_in.reload()?;
+        self.vault_out.reload()?;
+        let (reserve_x, reserve_y) = match is_x {
+            true => (self.vault_in.amount, self.vault_out.amount),
+            false => (self.vault_out.amount, self.vault_in.amount),
+        };
+        self.oracle.update(&self.config, now, reserve_x, reserve_y);
+        let fee_bps = self.config.swap_fee();
+        self.config.record_price_move(before, (reserve_x, reserve_y), now);
+        emit!(Swapped {
+            config: self.config.key(),
+            user: self.owner.key(),
+            is_x,
+            amount_in,
+            amount_out: res.withdraw,
+            fee: res.fee,
+            fee_bps,
+            protocol_fee,
+            reserve_x,
+            reserve_y,
+        });
+        emit!(OrderFilled {
+            config: self.config.key(),
+            order: self.order.key(),
+            owner: self.owner.key(),
+            keeper: self.keeper.key(),
+            amount_in,
+            amount_out,
+            tip,
+        });
+        self.config.open_orders = self.config.open_orders.saturating_sub(1);
+        Ok(())
+    }
+
+    // moves the whole escrow into the pool and closes it, rent to the owner
+    pub fn release_escrow(&self, amount: u64) -> Result<()> {
+        let config = self.config.key();
+        let owner = self.owner.key();
+        let id = self.order.id.to_le_bytes();
+        let seeds = &[
+            &b"order"[..],
+            config.as_ref(),
+            owner.as_ref(),
+            id.as_ref(),
+            &[self.order.bump],
+        ];
+        let signer_seeds = &[&seeds[..]];
+        transfer_tokens(
+            self.escrow.to_account_info(),
+            self.vault_in.to_account_info(),
+            &self.mint_in,
+            self.order.to_account_info(),
+            self.token_program_in.to_account_info(),
+            amount,
+            signer_seeds,
+        )?;
+        let accounts = CloseAccount {
+            account: self.escrow.to_account_info(),
+            destination: self.owner.to_account_info(),
+            authority: self.order.to_account_info(),
+        };
+        close_account(CpiContext::new_with_signer(
+            self.token_program_in.to_account_info(),
+            accounts,
+            signer_seeds,
+        ))
+    }
+
+    // pays out of the vaults, the protocol fee from the input side or the output to the owner
+    pub fn withdraw_from_vault(&self, to_treasury: bool, amount: u64) -> Result<()> {
+        let (from, to, mint, token_program) = match to_treasury {
+            true => (
+                self.vault_in.to_account_info(),
+                self.treasury_in.to_account_info(),
+                &self.mint_in,
+                self.token_program_in.to_account_info(),
+            ),
+            false => (
+                self.vault_out.to_account_info(),
+                self.owner_out.to_account_info(),
+                &self.mint_out,
+                self.token_program_out.to_account_info(),
+            ),
+        };
+        let seeds = &[
+            &b"config"[..],
+            &self.config.seed.to_le_bytes(),
+            &[self.config.config_bump],
+        ];
+        transfer_tokens(
+            from,
+            to,
+            mint,
+            self.config.to_account_info(),
+            token_program,
+            amount,
+            &[&seeds[..]],
+        )
+    }
+}
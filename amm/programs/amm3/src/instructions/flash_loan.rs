@@ -0,0 +1,196 @@
+use crate::constants::MAX_BPS;
+use crate::error::AmmError;
+use crate::events::{FlashLoanRepaid, FlashLoanTaken};
+use crate::curve::{mul_div, Rounding};
+use crate::state::{AllowlistEntry, Config};
+use crate::utils::{inverse_transfer_fee, transfer_tokens};
+use anchor_lang::{
+    prelude::*,
+    solana_program::sysvar::instructions::{
+        load_current_index_checked, load_instruction_at_checked,
+    },
+    Discriminator,
+};
+use anchor_spl::token_interface::{Mint, TokenAccount, TokenInterface};
+
+#[derive(Accounts)]
+pub struct FlashLoan<'info> {
+    pub borrower: Signer<'info>,
+    #[account(
+        mint::token_program = token_program_x,
+    )]
+    pub mint_x: Box<InterfaceAccount<'info, Mint>>,
+    #[account(
+        mint::token_program = token_program_y,
+    )]
+    pub mint_y: Box<InterfaceAccount<'info, Mint>>,
+    #[account(
+        mut,
+        has_one = mint_x,
+        has_one = mint_y,
+        seeds= [b"config", config.seed.to_le_bytes().as_ref()],
+        bump= config.config_bump,
+    )]
+    pub config: Box<Account<'info, Config>>,
+    #[account(
+        mut,
+        associated_token::mint= mint_x,
+        associated_token::authority= config,
+        associated_token::token_program= token_program_x,
+    )]
+    pub vault_x: Box<InterfaceAccount<'info, TokenAccount>>,
+    #[account(
+        mut,
+        associated_token::mint= mint_y,
+        associated_token::authority= config,
+        associated_token::token_program= token_program_y,
+    )]
+    pub vault_y: Box<InterfaceAccount<'info, TokenAccount>>,
+    // receives the loan, and pays it back on repay
+    #[account(mut)]
+    pub borrower_token: Box<InterfaceAccount<'info, TokenAccount>>,
+    /// CHECK: address checked against the instructions sysvar
+    #[account(address = anchor_lang::solana_program::sysvar::instructions::ID)]
+    pub instructions: UncheckedAccount<'info>,
+    pub token_program_x: Interface<'info, TokenInterface>,
+    pub token_program_y: Interface<'info, TokenInterface>,
+    // the borrower's entry, needed only while the pool is in allowlist mode
+    pub allowlist_entry: Option<Box<Account<'info, AllowlistEntry>>>,
+}
+
+impl<'info> FlashLoan<'info> {
+    pub fn flash_loan(&mut self, is_x: bool, amount: u64) -> Result<()> {
+        self.config.check_unlocked()?;
+        self.config.check_allowlisted(
+            &self.config.key(),
+            &self.borrower.key(),
+            self.allowlist_entry.as_deref(),
+        )?;
+        require!(amount > 0, AmmError::InvalidAmount);
+        self.check_borrower_token(is_x)?;
+        let vault = match is_x {
+            true => &self.vault_x,
+            false => &self.vault_y,
+        };
+        require!(amount <= vault.amount, AmmError::Insufficientbalance);
+        self.check_repaid()?;
+
+        self.config.flash_loan_amount = amount;
+        self.config.flash_loan_is_x = is_x;
+
+        let (from, mint, token_program) = match is_x {
+            true => (
+                self.vault_x.to_account_info(),
+                &self.mint_x,
+                self.token_program_x.to_account_info(),
+            ),
+            false => (
+                self.vault_y.to_account_info(),
+                &self.mint_y,
+                self.token_program_y.to_account_info(),
+            ),
+        };
+        let seeds = &[
+            &b"config"[..],
+            &self.config.seed.to_le_bytes(),
+            &[self.config.config_bump],
+        ];
+        let signer_seeds = &[&seeds[..]];
+        transfer_tokens(
+            from,
+            self.borrower_token.to_account_info(),
+            mint,
+            self.config.to_account_info(),
+            token_program,
+            amount,
+            signer_seeds,
+        )?;
+        emit!(FlashLoanTaken {
+            config: self.config.key(),
+            borrower: self.borrower.key(),
+            is_x,
+            amount,
+        });
+        Ok(())
+    }
+
+    pub fn flash_repay(&mut self) -> Result<()> {
+        let amount = self.config.flash_loan_amount;
+        require!(amount > 0, AmmError::NoFlashLoan);
+        let is_x = self.config.flash_loan_is_x;
+        self.check_borrower_token(is_x)?;
+
+        // the fee stays in the vault for LPs
+        let fee = mul_div(
+            amount,
+            self.config.flash_fee as u64,
+            MAX_BPS as u64,
+            Rounding::Up,
+        )?;
+        let owed = amount.checked_add(fee).ok_or(AmmError::Overflow)?;
+        let (to, mint, token_program) = match is_x {
+            true => (
+                self.vault_x.to_account_info(),
+                &self.mint_x,
+                self.token_program_x.to_account_info(),
+            ),
+            false => (
+                self.vault_y.to_account_info(),
+                &self.mint_y,
+                self.token_program_y.to_account_info(),
+            ),
+        };
+        let gross = owed
+            .checked_add(inverse_transfer_fee(&mint.to_account_info(), owed)?)
+            .ok_or(AmmError::Overflow)?;
+        transfer_tokens(
+            self.borrower_token.to_account_info(),
+            to,
+            mint,
+            self.borrower.to_account_info(),
+            token_program,
+            gross,
+            &[],
+        )?;
+
+        self.config.flash_loan_amount = 0;
+        emit!(FlashLoanRepaid {
+            config: self.config.key(),
+            borrower: self.borrower.key(),
+            is_x,
+            amount,
+            fee,
+        });
+        Ok(())
+    }
+
+    pub fn check_borrower_token(&self, is_x: bool) -> Result<()> {
+        let mint = match is_x {
+            true => self.mint_x.key(),
+            false => self.mint_y.key(),
+        };
+        require_keys_eq!(self.borrower_token.mint, mint, AmmError::InvalidToken);
+        Ok(())
+    }
+
+    // the loan has to be taken by a top-level instruction and repaid by a later
+    // top-level flash_repay on the same pool
+    pub fn check_repaid(&self) -> Result<()> {
+        let instructions = self.instructions.to_account_info();
+        let current = load_current_index_checked(&instructions)? as usize;
+        let current_ix = load_instruction_at_checked(current, &instructions)?;
+        require_keys_eq!(current_ix.program_id, crate::ID, AmmError::FlashLoanNotRepaid);
+
+        let mut index = current + 1;
+        while let Ok(ix) = load_instruction_at_checked(index, &instructions) {
+            if ix.program_id == crate::ID
+                && ix.data.starts_with(crate::instruction::FlashRepay::DISCRIMINATOR)
+                && ix.accounts.iter().any(|meta| meta.pubkey == self.config.key())
+            {
+                return Ok(());
+            }
+            index += 1;
+        }
+        err!(AmmError::FlashLoanNotRepaid)
+    }
+}
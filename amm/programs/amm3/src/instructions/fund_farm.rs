@@ -0,0 +1,75 @@
+use crate::error::AmmError;
+use crate::events::FarmFunded;
+use crate::state::{Config, Farm};
+use crate::utils::{transfer_fee, transfer_tokens};
+use anchor_lang::prelude::*;
+use anchor_spl::token_interface::{Mint, TokenAccount, TokenInterface};
+
+#[derive(Accounts)]
+pub struct FundFarm<'info> {
+    pub authority: Signer<'info>,
+    #[account(
+        seeds= [b"config", config.seed.to_le_bytes().as_ref()],
+        bump= config.config_bump,
+    )]
+    pub config: Box<Account<'info, Config>>,
+    #[account(
+        mint::token_program = reward_token_program,
+    )]
+    pub reward_mint: Box<InterfaceAccount<'info, Mint>>,
+    #[account(
+        mut,
+        has_one = config,
+        has_one = reward_mint,
+        seeds = [b"farm", config.key().as_ref()],
+        bump = farm.bump,
+    )]
+    pub farm: Box<Account<'info, Farm>>,
+    #[account(
+        mut,
+        associated_token::mint= reward_mint,
+        associated_token::authority= farm,
+        associated_token::token_program= reward_token_program,
+    )]
+    pub reward_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+    #[account(
+        mut,
+        token::mint = reward_mint,
+        token::authority = authority,
+        token::token_program = reward_token_program,
+    )]
+    pub source: Box<InterfaceAccount<'info, TokenAccount>>,
+    pub reward_token_program: Interface<'info, TokenInterface>,
+}
+
+impl<'info> FundFarm<'info> {
+    // tops up the rewards and/or pushes out `end_time`; the emission rate is recomputed so
+    // everything not yet emitted runs out exactly at `end_time`
+    pub fn fund_farm(&mut self, amount: u64, end_time: i64) -> Result<()> {
+        self.config.check_authority(&self.authority.key())?;
+        let now = Clock::get()?.unix_timestamp;
+        let received = amount
+            .checked_sub(transfer_fee(&self.reward_mint.to_account_info(), amount)?)
+            .ok_or(AmmError::Underflow)?;
+        self.farm.fund(now, received, end_time)?;
+        if amount > 0 {
+            transfer_tokens(
+                self.source.to_account_info(),
+                self.reward_vault.to_account_info(),
+                &self.reward_mint,
+                self.authority.to_account_info(),
+                self.reward_token_program.to_account_info(),
+                amount,
+                &[],
+            )?;
+        }
+        emit!(FarmFunded {
+            config: self.config.key(),
+            farm: self.farm.key(),
+            amount: received,
+            emission_rate: self.farm.emission_rate,
+            end_time,
+        });
+        Ok(())
+    }
+}
@@ -0,0 +1,199 @@
+use crate::constants::MINIMUM_LIQUIDITY;
+use crate::curve::{initial_liquidity, mul_div, Rounding};
+use crate::error::AmmError;
+use crate::events::Graduated;
+use crate::state::{Config, LaunchParams, PoolType};
+use crate::utils::transfer_tokens;
+use anchor_lang::prelude::*;
+use anchor_spl::token_interface::{mint_to, Mint, MintTo, TokenAccount, TokenInterface};
+
+#[derive(Accounts)]
+pub struct Graduate<'info> {
+    pub caller: Signer<'info>,
+    #[account(
+        mint::token_program = token_program_x,
+    )]
+    pub mint_x: Box<InterfaceAccount<'info, Mint>>,
+    #[account(
+        mint::token_program = token_program_y,
+    )]
+    pub mint_y: Box<InterfaceAccount<'info, Mint>>,
+    #[account(
+        mut,
+        has_one = mint_x,
+        has_one = mint_y,
+        seeds= [b"config", config.seed.to_le_bytes().as_ref()],
+        bump= config.config_bump,
+    )]
+    pub config: Box<Account<'info, Config>>,
+    #[account(
+        mut,
+        seeds= [b"lp", config.key().as_ref()],
+        bump= config.lp_bump,
+        mint::token_program = token_program,
+    )]
+    pub mint_lp: Box<InterfaceAccount<'info, Mint>>,
+    #[account(
+        mut,
+        seeds=[b"locked", config.key().as_ref()],
+        bump=config.locked_lp_bump,
+    )]
+    pub locked_lp: Box<InterfaceAccount<'info, TokenAccount>>,
+    #[account(
+        mut,
+        associated_token::mint= mint_x,
+        associated_token::authority= config,
+        associated_token::token_program= token_program_x,
+    )]
+    pub vault_x: Box<InterfaceAccount<'info, TokenAccount>>,
+    #[account(
+        mut,
+        associated_token::mint= mint_y,
+        associated_token::authority= config,
+        associated_token::token_program= token_program_y,
+    )]
+    pub vault_y: Box<InterfaceAccount<'info, TokenAccount>>,
+    #[account(
+        mut,
+        seeds= [b"treasury", config.key().as_ref(), mint_x.key().as_ref()],
+        bump= config.treasury_x_bump,
+    )]
+    pub treasury_x: Box<InterfaceAccount<'info, TokenAccount>>,
+    #[account(
+        mut,
+        seeds= [b"treasury", config.key().as_ref(), mint_y.key().as_ref()],
+        bump= config.treasury_y_bump,
+    )]
+    pub treasury_y: Box<InterfaceAccount<'info, TokenAccount>>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub token_program_x: Interface<'info, TokenInterface>,
+    pub token_program_y: Interface<'info, TokenInterface>,
+}
+
+impl<'info> Graduate<'info> {
+    // anyone may graduate a launch once both vaults hold their target. The virtual reserves
+    // go away, so whichever side would then be too large for the curve's last price is
+    // cut down to it and the surplus sent to the treasury; the rest seeds the
+    // constant-product pool and all of its LP is locked
+    pub fn graduate(&mut self) -> Result<()> {
+        self.config.check_unlocked()?;
+        require!(
+            self.config.pool_type == PoolType::Launch,
+            AmmError::InvalidPoolType
+        );
+        let launch = self.config.launch;
+        let (reserve_x, reserve_y) = (self.vault_x.amount, self.vault_y.amount);
+        require!(
+            reserve_x >= launch.target_x && reserve_y >= launch.target_y,
+            AmmError::LaunchTargetNotReached
+        );
+        require!(reserve_x != 0 && reserve_y != 0, AmmError::ZeroBalance);
+
+        // price on the curve is (y + vy) / (x + vx), keep the side that is short of it whole
+        let curve_x = reserve_x
+            .checked_add(launch.virtual_x)
+            .ok_or(AmmError::Overflow)?;
+        let curve_y = reserve_y
+            .checked_add(launch.virtual_y)
+            .ok_or(AmmError::Overflow)?;
+        let x_heavy = (reserve_y as u128) * (launch.virtual_x as u128)
+            <= (reserve_x as u128) * (launch.virtual_y as u128);
+        let (amount_x, amount_y) = match x_heavy {
+            true => (
+                mul_div(reserve_y, curve_x, curve_y, Rounding::Down)?,
+                reserve_y,
+            ),
+            false => (
+                reserve_x,
+                mul_div(reserve_x, curve_y, curve_x, Rounding::Down)?,
+            ),
+        };
+        let (surplus_x, surplus_y) = (reserve_x - amount_x, reserve_y - amount_y);
+
+        let liquidity = initial_liquidity(
+            amount_x,
+            amount_y,
+            self.config.decimals_x,
+            self.config.decimals_y,
+            self.config.lp_decimals,
+        )?;
+        require!(
+            liquidity > MINIMUM_LIQUIDITY,
+            AmmError::LiquidityLessThanMinium
+        );
+
+        if surplus_x > 0 {
+            self.send_to_treasury(true, surplus_x)?;
+        }
+        if surplus_y > 0 {
+            self.send_to_treasury(false, surplus_y)?;
+        }
+        self.mint_locked_lp(liquidity)?;
+
+        self.config.pool_type = PoolType::ConstantProduct;
+        self.config.launch = LaunchParams::default();
+        emit!(Graduated {
+            config: self.config.key(),
+            amount_x,
+            amount_y,
+            surplus_x,
+            surplus_y,
+            lp_amount: liquidity,
+        });
+        Ok(())
+    }
+
+    pub fn send_to_treasury(&self, is_x: bool, amount: u64) -> Result<()> {
+        let (from, to, mint, token_program) = match is_x {
+            true => (
+                self.vault_x.to_account_info(),
+                self.treasury_x.to_account_info(),
+                &self.mint_x,
+                self.token_program_x.to_account_info(),
+            ),
+            false => (
+                self.vault_y.to_account_info(),
+                self.treasury_y.to_account_info(),
+                &self.mint_y,
+                self.token_program_y.to_account_info(),
+            ),
+        };
+        let seeds = &[
+            &b"config"[..],
+            &self.config.seed.to_le_bytes(),
+            &[self.config.config_bump],
+        ];
+        let signer_seeds = &[&seeds[..]];
+        transfer_tokens(
+            from,
+            to,
+            mint,
+            self.config.to_account_info(),
+            token_program,
+            amount,
+            signer_seeds,
+        )
+    }
+
+    pub fn mint_locked_lp(&self, amount: u64) -> Result<()> {
+        let accounts = MintTo {
+            mint: self.mint_lp.to_account_info(),
+            to: self.locked_lp.to_account_info(),
+            authority: self.config.to_account_info(),
+        };
+        let seeds = &[
+            &b"config"[..],
+            &self.config.seed.to_le_bytes(),
+            &[self.config.config_bump],
+        ];
+        let signer_seeds = &[&seeds[..]];
+        mint_to(
+            CpiContext::new_with_signer(
+                self.token_program.to_account_info(),
+                accounts,
+                signer_seeds,
+            ),
+            amount,
+        )
+    }
+}
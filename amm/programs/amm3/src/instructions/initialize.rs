@@ -1,40 +1,87 @@
-use crate::state::Config;
+use crate::constants::{DEFAULT_FLASH_FEE, MAX_AMP, MAX_BPS, MAX_LP_DECIMALS, MIN_AMP};
+use crate::error::AmmError;
+use crate::state::{
+    Config, DynamicFee, Factory, FeeTiers, LaunchParams, Observation, Oracle, Pair, PoolIndex,
+    PoolType,
+};
+use crate::utils::check_mint_extensions;
 use anchor_lang::prelude::*;
 use anchor_spl::{
     associated_token::AssociatedToken,
-    token::{Mint, Token, TokenAccount},
+    token_interface::{Mint, TokenAccount, TokenInterface},
 };
 
 #[derive(Accounts)]
-#[instruction(seed:u64)]
+#[instruction(seed: u64, lp_decimals: u8, fee: u16)]
 pub struct Initialize<'info> {
     #[account(mut)]
     pub initializer: Signer<'info>,
-    pub mint_x: Account<'info, Mint>,
-    pub mint_y: Account<'info, Mint>,
+    #[account(
+        mint::token_program = token_program_x,
+    )]
+    pub mint_x: Box<InterfaceAccount<'info, Mint>>,
+    #[account(
+        mint::token_program = token_program_y,
+    )]
+    pub mint_y: Box<InterfaceAccount<'info, Mint>>,
     #[account(
         init,
         payer=initializer,
         seeds=[b"lp",config.key().as_ref()],
         bump,
-        mint::decimals =6,
+        mint::decimals = lp_decimals,
         mint::authority = config,
+        mint::token_program = token_program,
+    )]
+    pub mint_lp: Box<InterfaceAccount<'info, Mint>>,
+    // holds the minimum liquidity minted on the first deposit; the account is its own
+    // authority and nothing ever signs for it, so those LP tokens can never move
+    #[account(
+        init,
+        payer = initializer,
+        seeds = [b"locked", config.key().as_ref()],
+        bump,
+        token::mint = mint_lp,
+        token::authority = locked_lp,
+        token::token_program = token_program,
     )]
-    pub mint_lp: Account<'info, Mint>,
+    pub locked_lp: Box<InterfaceAccount<'info, TokenAccount>>,
     #[account(
         init,
         payer = initializer,
         associated_token::mint= mint_x,
         associated_token::authority= config,
+        associated_token::token_program= token_program_x,
     )]
-    pub vault_x: Account<'info, TokenAccount>,
+    pub vault_x: Box<InterfaceAccount<'info, TokenAccount>>,
     #[account(
         init,
         payer = initializer,
         associated_token::mint= mint_y,
         associated_token::authority= config,
+        associated_token::token_program= token_program_y,
     )]
-    pub vault_y: Account<'info, TokenAccount>,
+    pub vault_y: Box<InterfaceAccount<'info, TokenAccount>>,
+    #[account(
+        init,
+        payer = initializer,
+        seeds = [b"treasury", config.key().as_ref(), mint_x.key().as_ref()],
+        bump,
+        token::mint = mint_x,
+        token::authority = config,
+        token::token_program = token_program_x,
+    )]
+    pub treasury_x: Box<InterfaceAccount<'info, TokenAccount>>,
+    #[account(
+        init,
+        payer = initializer,
+        seeds = [b"treasury", config.key().as_ref(), mint_y.key().as_ref()],
+        bump,
+        token::mint = mint_y,
+        token::authority = config,
+        token::token_program = token_program_y,
+    )]
+    pub treasury_y: Box<InterfaceAccount<'info, TokenAccount>>,
     #[account(
         init,
         payer= initializer,
@@ -42,30 +89,162 @@ pub struct Initialize<'info> {
         bump,
         space = 8+ Config::INIT_SPACE,
     )]
-    pub config: Account<'info, Config>,
-    pub token_program: Program<'info, Token>,
+    pub config: Box<Account<'info, Config>>,
+    #[account(
+        init,
+        payer = initializer,
+        seeds = [b"oracle", config.key().as_ref()],
+        bump,
+        space = 8 + Oracle::INIT_SPACE,
+    )]
+    pub oracle: Box<Account<'info, Oracle>>,
+    #[account(
+        init_if_needed,
+        payer = initializer,
+        seeds = [b"factory"],
+        bump,
+        space = 8 + Factory::INIT_SPACE,
+    )]
+    pub factory: Box<Account<'info, Factory>>,
+    #[account(
+        seeds = [b"fee_tiers"],
+        bump = fee_tiers.bump,
+    )]
+    pub fee_tiers: Box<Account<'info, FeeTiers>>,
+    // fails for a second pool on the same pair and fee tier
+    #[account(
+        init,
+        payer = initializer,
+        seeds = [b"pair", mint_x.key().as_ref(), mint_y.key().as_ref(), fee.to_le_bytes().as_ref()],
+        bump,
+        space = 8 + Pair::INIT_SPACE,
+    )]
+    pub pair: Box<Account<'info, Pair>>,
+    #[account(
+        init_if_needed,
+        payer = initializer,
+        seeds = [b"pool_index", factory.next_page().to_le_bytes().as_ref()],
+        bump,
+        space = 8 + PoolIndex::INIT_SPACE,
+    )]
+    pub pool_index: Box<Account<'info, PoolIndex>>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub token_program_x: Interface<'info, TokenInterface>,
+    pub token_program_y: Interface<'info, TokenInterface>,
     pub associated_token_program: Program<'info, AssociatedToken>,
     pub system_program: Program<'info, System>,
 }
 
 impl<'info> Initialize<'info> {
+    #[allow(clippy::too_many_arguments)]
     pub fn init(
         &mut self,
         seed: u64,
+        lp_decimals: u8,
         fee: u16,
+        protocol_fee: u16,
         authority: Option<Pubkey>,
+        pool_type: PoolType,
+        amp: u64,
+        launch: Option<LaunchParams>,
         bumps: InitializeBumps,
     ) -> Result<()> {
+        self.fee_tiers.check_fee(fee)?;
+        require!(protocol_fee <= MAX_BPS, AmmError::InvalidFee);
+        require!(lp_decimals <= MAX_LP_DECIMALS, AmmError::InvalidPrecision);
+        require!(
+            self.mint_x.key() < self.mint_y.key(),
+            AmmError::InvalidMintOrder
+        );
+        if pool_type == PoolType::StableSwap {
+            require!((MIN_AMP..=MAX_AMP).contains(&amp), AmmError::InvalidAmp);
+        }
+        // launch pools take their parameters here; the launched supply is then sent straight
+        // to its vault, as nothing can be deposited for LP before graduation. Both virtual
+        // reserves have to be set, the curve has nothing else to price against until then
+        match (pool_type == PoolType::Launch, launch) {
+            (true, Some(launch)) => {
+                require!(
+                    launch.virtual_x > 0 && launch.virtual_y > 0,
+                    AmmError::InvalidAmount
+                );
+                require!(
+                    launch.target_x > 0 || launch.target_y > 0,
+                    AmmError::InvalidAmount
+                );
+            }
+            (false, None) => {}
+            _ => return err!(AmmError::InvalidPoolType),
+        }
+        check_mint_extensions(&self.mint_x.to_account_info())?;
+        check_mint_extensions(&self.mint_y.to_account_info())?;
         self.config.set_inner(Config {
             seed,
             authority,
+            initializer: self.initializer.key(),
             mint_x: self.mint_x.key(),
             mint_y: self.mint_y.key(),
+            decimals_x: self.mint_x.decimals,
+            decimals_y: self.mint_y.decimals,
+            lp_decimals,
             fee,
+            protocol_fee,
+            referral_fee: 0,
             locked: false,
+            allowlist: false,
+            flash_fee: DEFAULT_FLASH_FEE,
+            flash_loan_amount: 0,
+            flash_loan_is_x: false,
+            pool_type,
+            amp_initial: amp,
+            amp_target: amp,
+            amp_ramp_start: 0,
+            amp_ramp_end: 0,
+            launch: launch.unwrap_or_default(),
+            dynamic_fee: DynamicFee::default(),
+            close_after: 0,
+            open_orders: 0,
             config_bump: bumps.config,
             lp_bump: bumps.mint_lp,
+            locked_lp_bump: bumps.locked_lp,
+            treasury_x_bump: bumps.treasury_x,
+            treasury_y_bump: bumps.treasury_y,
+            oracle_bump: bumps.oracle,
+        });
+        self.oracle.set_inner(Oracle {
+            config: self.config.key(),
+            index: 0,
+            bump: bumps.oracle,
+            reserve_x: 0,
+            reserve_y: 0,
+            observations: vec![Observation {
+                timestamp: Clock::get()?.unix_timestamp,
+                price_x_cumulative: 0,
+                price_y_cumulative: 0,
+            }],
+        });
+        self.register(bumps)
+    }
+
+    pub fn register(&mut self, bumps: InitializeBumps) -> Result<()> {
+        self.pair.set_inner(Pair {
+            config: self.config.key(),
+            mint_x: self.mint_x.key(),
+            mint_y: self.mint_y.key(),
+            fee: self.config.fee,
+            bump: bumps.pair,
         });
+        if self.pool_index.pools.is_empty() {
+            self.pool_index.page = self.factory.next_page();
+            self.pool_index.bump = bumps.pool_index;
+        }
+        self.pool_index.pools.push(self.config.key());
+        self.factory.bump = bumps.factory;
+        self.factory.pool_count = self
+            .factory
+            .pool_count
+            .checked_add(1)
+            .ok_or(AmmError::Overflow)?;
         Ok(())
     }
 }
@@ -1,9 +1,47 @@
+pub mod allowlist;
+pub mod cancel_order;
+pub mod close_farm;
+pub mod close_pool;
+pub mod close_stake;
+pub mod collect_protocol_fees;
+pub mod create_farm;
 pub mod deposit;
+pub mod fee_tiers;
+pub mod fill_order;
+pub mod flash_loan;
+pub mod fund_farm;
+pub mod graduate;
 pub mod initialize;
+pub mod observe;
+pub mod place_order;
+pub mod redeem;
+pub mod route_swap;
+pub mod stake;
 pub mod swap;
+pub mod update;
 pub mod withdraw;
+pub mod zap;
 
+pub use allowlist::*;
+pub use cancel_order::*;
+pub use close_farm::*;
+pub use close_pool::*;
+pub use close_stake::*;
+pub use collect_protocol_fees::*;
+pub use create_farm::*;
 pub use deposit::*;
+pub use fee_tiers::*;
+pub use fill_order::*;
+pub use flash_loan::*;
+pub use fund_farm::*;
+pub use graduate::*;
 pub use initialize::*;
+pub use observe::*;
+pub use place_order::*;
+pub use redeem::*;
+pub use route_swap::*;
+pub use stake::*;
 pub use swap::*;
+pub use update::*;
 pub use withdraw::*;
+pub use zap::*;
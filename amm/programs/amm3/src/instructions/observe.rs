@@ -0,0 +1,24 @@
+use crate::state::{Config, Oracle, Twap};
+use anchor_lang::prelude::*;
+
+#[derive(Accounts)]
+pub struct Observe<'info> {
+    #[account(
+        seeds= [b"config", config.seed.to_le_bytes().as_ref()],
+        bump= config.config_bump,
+    )]
+    pub config: Account<'info, Config>,
+    #[account(
+        seeds= [b"oracle", config.key().as_ref()],
+        bump= config.oracle_bump,
+    )]
+    pub oracle: Account<'info, Oracle>,
+}
+
+impl<'info> Observe<'info> {
+    // time-weighted average prices over the last `window` seconds
+    pub fn observe(&self, window: u32) -> Result<Twap> {
+        self.oracle
+            .observe(&self.config, Clock::get()?.unix_timestamp, window)
+    }
+}
@@ -0,0 +1,139 @@
+use crate::constants::MAX_ORDER_DURATION;
+use crate::error::AmmError;
+use crate::events::OrderPlaced;
+use crate::state::{Config, Order};
+use crate::utils::transfer_tokens;
+use anchor_lang::prelude::*;
+use anchor_lang::system_program::{transfer, Transfer};
+use anchor_spl::{
+    associated_token::AssociatedToken,
+    token_interface::{Mint, TokenAccount, TokenInterface},
+};
+
+#[derive(Accounts)]
+#[instruction(id: u64)]
+pub struct PlaceOrder<'info> {
+    #[account(mut)]
+    pub owner: Signer<'info>,
+    #[account(
+        mint::token_program = token_program_in,
+    )]
+    pub mint_in: Box<InterfaceAccount<'info, Mint>>,
+    pub mint_out: Box<InterfaceAccount<'info, Mint>>,
+    #[account(
+        mut,
+        seeds= [b"config", config.seed.to_le_bytes().as_ref()],
+        bump= config.config_bump,
+    )]
+    pub config: Box<Account<'info, Config>>,
+    #[account(
+        init,
+        payer = owner,
+        seeds = [b"order", config.key().as_ref(), owner.key().as_ref(), id.to_le_bytes().as_ref()],
+        bump,
+        space = 8 + Order::INIT_SPACE,
+    )]
+    pub order: Box<Account<'info, Order>>,
+    #[account(
+        init,
+        payer = owner,
+        associated_token::mint= mint_in,
+        associated_token::authority= order,
+        associated_token::token_program= token_program_in,
+    )]
+    pub escrow: Box<InterfaceAccount<'info, TokenAccount>>,
+    #[account(
+        mut,
+        associated_token::mint= mint_in,
+        associated_token::authority= owner,
+        associated_token::token_program= token_program_in,
+    )]
+    pub owner_in: Box<InterfaceAccount<'info, TokenAccount>>,
+    pub token_program_in: Interface<'info, TokenInterface>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+}
+
+impl<'info> PlaceOrder<'info> {
+    #[allow(clippy::too_many_arguments)]
+    pub fn place_order(
+        &mut self,
+        id: u64,
+        amount_in: u64,
+        min_out: u64,
+        tip: u64,
+        expires_at: i64,
+        bumps: PlaceOrderBumps,
+    ) -> Result<()> {
+        require!(amount_in != 0 && min_out != 0, AmmError::InvalidAmount);
+        self.config.check_not_closing()?;
+        // keepers fill on the owner's behalf, which allowlisted pools do not allow
+        require!(!self.config.allowlist, AmmError::NotAllowlisted);
+        let now = Clock::get()?.unix_timestamp;
+        require!(expires_at > now, AmmError::OrderExpired);
+        require!(
+            expires_at <= now.saturating_add(MAX_ORDER_DURATION),
+            AmmError::OrderExpiryTooFar
+        );
+        let (mint_in, mint_out) = (self.mint_in.key(), self.mint_out.key());
+        let pair = (self.config.mint_x, self.config.mint_y);
+        let is_x = if (mint_in, mint_out) == pair {
+            true
+        } else if (mint_out, mint_in) == pair {
+            false
+        } else {
+            return err!(AmmError::InvalidToken);
+        };
+
+        transfer_tokens(
+            self.owner_in.to_account_info(),
+            self.escrow.to_account_info(),
+            &self.mint_in,
+            self.owner.to_account_info(),
+            self.token_program_in.to_account_info(),
+            amount_in,
+            &[],
+        )?;
+        if tip > 0 {
+            let accounts = Transfer {
+                from: self.owner.to_account_info(),
+                to: self.order.to_account_info(),
+            };
+            transfer(
+                CpiContext::new(self.system_program.to_account_info(), accounts),
+                tip,
+            )?;
+        }
+        self.config.open_orders = self
+            .config
+            .open_orders
+            .checked_add(1)
+            .ok_or(AmmError::Overflow)?;
+        // the order is for what the escrow actually holds after any transfer fee
+        self.escrow.reload()?;
+        self.order.set_inner(Order {
+            config: self.config.key(),
+            owner: self.owner.key(),
+            id,
+            mint_in,
+            mint_out,
+            is_x,
+            amount_in: self.escrow.amount,
+            min_out,
+            tip,
+            expires_at,
+            bump: bumps.order,
+        });
+        emit!(OrderPlaced {
+            config: self.config.key(),
+            order: self.order.key(),
+            owner: self.owner.key(),
+            is_x,
+            amount_in: self.escrow.amount,
+            min_out,
+            tip,
+            expires_at,
+        });
+        Ok(())
+    }
+}
@@ -0,0 +1,154 @@
+use crate::curve::{mul_div, Rounding};
+use crate::error::AmmError;
+use crate::events::Redeemed;
+use crate::utils::transfer_tokens;
+use anchor_lang::prelude::*;
+use anchor_spl::{
+    associated_token::AssociatedToken,
+    token_interface::{
+        burn, close_account, Burn, CloseAccount, Mint, TokenAccount, TokenInterface,
+    },
+};
+
+#[derive(Accounts)]
+pub struct Redeem<'info> {
+    #[account(mut)]
+    pub user: Signer<'info>,
+    /// CHECK: address of the closed pool, only used to derive the accounts it left behind
+    pub config: UncheckedAccount<'info>,
+    #[account(
+        mint::token_program = token_program_x,
+    )]
+    pub mint_x: Box<InterfaceAccount<'info, Mint>>,
+    #[account(
+        mint::token_program = token_program_y,
+    )]
+    pub mint_y: Box<InterfaceAccount<'info, Mint>>,
+    #[account(
+        mut,
+        seeds= [b"lp", config.key().as_ref()],
+        bump,
+        mint::token_program = token_program,
+    )]
+    pub mint_lp: Box<InterfaceAccount<'info, Mint>>,
+    #[account(
+        mut,
+        associated_token::mint= mint_lp,
+        associated_token::authority= user,
+        associated_token::token_program= token_program,
+    )]
+    pub user_lp: Box<InterfaceAccount<'info, TokenAccount>>,
+    // only `close_pool` creates these, so they exist once the pool is gone
+    #[account(
+        mut,
+        seeds = [b"exit", config.key().as_ref(), mint_x.key().as_ref()],
+        bump,
+    )]
+    pub exit_x: Box<InterfaceAccount<'info, TokenAccount>>,
+    #[account(
+        mut,
+        seeds = [b"exit", config.key().as_ref(), mint_y.key().as_ref()],
+        bump,
+    )]
+    pub exit_y: Box<InterfaceAccount<'info, TokenAccount>>,
+    #[account(
+        init_if_needed,
+        payer = user,
+        associated_token::mint= mint_x,
+        associated_token::authority= user,
+        associated_token::token_program= token_program_x,
+    )]
+    pub user_x: Box<InterfaceAccount<'info, TokenAccount>>,
+    #[account(
+        init_if_needed,
+        payer = user,
+        associated_token::mint= mint_y,
+        associated_token::authority= user,
+        associated_token::token_program= token_program_y,
+    )]
+    pub user_y: Box<InterfaceAccount<'info, TokenAccount>>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub token_program_x: Interface<'info, TokenInterface>,
+    pub token_program_y: Interface<'info, TokenInterface>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+}
+
+impl<'info> Redeem<'info> {
+    // burns LP of a pool closed by a forced exit for its share of what the vaults held; the
+    // last LP out also closes the exit accounts
+    pub fn redeem(&mut self, amount: u64, bumps: RedeemBumps) -> Result<()> {
+        require!(amount > 0, AmmError::InvalidAmount);
+        require!(amount <= self.user_lp.amount, AmmError::Insufficientbalance);
+        let supply = self.mint_lp.supply;
+        let amount_x = mul_div(self.exit_x.amount, amount, supply, Rounding::Down)?;
+        let amount_y = mul_div(self.exit_y.amount, amount, supply, Rounding::Down)?;
+
+        let accounts = Burn {
+            mint: self.mint_lp.to_account_info(),
+            from: self.user_lp.to_account_info(),
+            authority: self.user.to_account_info(),
+        };
+        burn(
+            CpiContext::new(self.token_program.to_account_info(), accounts),
+            amount,
+        )?;
+        let last = amount == supply;
+        self.pay_out(true, amount_x, bumps.exit_x, last)?;
+        self.pay_out(false, amount_y, bumps.exit_y, last)?;
+        emit!(Redeemed {
+            config: self.config.key(),
+            user: self.user.key(),
+            lp_amount: amount,
+            amount_x,
+            amount_y,
+        });
+        Ok(())
+    }
+
+    // pays `amount` out of an exit account, closing it to the user when `close` is set
+    pub fn pay_out(&self, is_x: bool, amount: u64, bump: u8, close: bool) -> Result<()> {
+        let (exit, to, mint, token_program) = match is_x {
+            true => (
+                &self.exit_x,
+                self.user_x.to_account_info(),
+                &self.mint_x,
+                self.token_program_x.to_account_info(),
+            ),
+            false => (
+                &self.exit_y,
+                self.user_y.to_account_info(),
+                &self.mint_y,
+                self.token_program_y.to_account_info(),
+            ),
+        };
+        let config = self.config.key();
+        let mint_key = mint.key();
+        let seeds = &[&b"exit"[..], config.as_ref(), mint_key.as_ref(), &[bump]];
+        let signer_seeds = &[&seeds[..]];
+        if amount > 0 {
+            transfer_tokens(
+                exit.to_account_info(),
+                to,
+                mint,
+                exit.to_account_info(),
+                token_program.clone(),
+                amount,
+                signer_seeds,
+            )?;
+        }
+        if !close {
+            return Ok(());
+        }
+        let accounts = CloseAccount {
+            account: exit.to_account_info(),
+            destination: self.user.to_account_info(),
+            authority: exit.to_account_info(),
+        };
+        close_account(CpiContext::new_with_signer(
+            token_program,
+            accounts,
+            signer_seeds,
+        ))
+    }
+}
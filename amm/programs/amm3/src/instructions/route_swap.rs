@@ -0,0 +1,232 @@
+use crate::error::AmmError;
+use crate::events::Swapped;
+use crate::state::{Config, Oracle};
+use crate::utils::{check_expiry, transfer_fee, transfer_tokens};
+use anchor_lang::prelude::*;
+use anchor_spl::{
+    associated_token::get_associated_token_address_with_program_id,
+    token::Token,
+    token_2022::Token2022,
+    token_interface::{Mint, TokenAccount},
+};
+
+// remaining accounts per hop: config, mint_in, mint_out, vault_in, vault_out, treasury_in, oracle, user_out;
+// all writable but the mints
+pub const HOP_ACCOUNTS: usize = 8;
+
+#[derive(Accounts)]
+pub struct RouteSwap<'info> {
+    #[account(mut)]
+    pub user: Signer<'info>,
+    #[account(
+        mut,
+        token::authority= user,
+    )]
+    pub user_src: InterfaceAccount<'info, TokenAccount>,
+    pub token_program: Program<'info, Token>,
+    pub token_program_2022: Program<'info, Token2022>,
+}
+
+impl<'info> RouteSwap<'info> {
+    pub fn route_swap(
+        &mut self,
+        hops: &'info [AccountInfo<'info>],
+        amount_in: u64,
+        min_out: u64,
+        expires_at: i64,
+    ) -> Result<()> {
+        let now = check_expiry(expires_at)?;
+        require!(amount_in > 0, AmmError::InvalidAmount);
+        require!(
+            !hops.is_empty() && hops.chunks_exact(HOP_ACCOUNTS).remainder().is_empty(),
+            AmmError::InvalidRoute
+        );
+
+        let mut source = self.user_src.to_account_info();
+        let mut mint_in = self.user_src.mint;
+        let mut amount = amount_in;
+        for hop in hops.chunks(HOP_ACCOUNTS) {
+            let (amount_out, mint_out) = self.swap_hop(hop, &source, mint_in, amount, now)?;
+            // the user's output account of this hop funds the next one
+            source = hop[7].to_account_info();
+            mint_in = mint_out;
+            amount = amount_out;
+        }
+        require!(amount >= min_out, AmmError::SlippageExceded);
+        Ok(())
+    }
+
+    // swaps `amount` of `mint_in` held in `source` through one pool,
+    // returns what reached the user's output account and its mint
+    pub fn swap_hop(
+        &self,
+        hop: &'info [AccountInfo<'info>],
+        source: &AccountInfo<'info>,
+        mint_in: Pubkey,
+        amount: u64,
+        now: i64,
+    ) -> Result<(u64, Pubkey)> {
+        let mut config = Account::<Config>::try_from(&hop[0])?;
+        require_keys_eq!(
+            config.key(),
+            Pubkey::create_program_address(
+                &[b"config", &config.seed.to_le_bytes(), &[config.config_bump]],
+                &crate::ID,
+            )
+            .map_err(|_| AmmError::InvalidRoute)?,
+            AmmError::InvalidRoute
+        );
+        config.check_unlocked()?;
+        // hops carry no allowlist entry, so routes cannot pass through allowlisted pools
+        require!(!config.allowlist, AmmError::NotAllowlisted);
+        config.refresh_dynamic_fee(Clock::get()?.slot);
+
+        let is_x = match mint_in {
+            m if m == config.mint_x => true,
+            m if m == config.mint_y => false,
+            _ => return err!(AmmError::InvalidToken),
+        };
+        let mint_out = match is_x {
+            true => config.mint_y,
+            false => config.mint_x,
+        };
+
+        let mint_in_account = InterfaceAccount::<Mint>::try_from(&hop[1])?;
+        let mint_out_account = InterfaceAccount::<Mint>::try_from(&hop[2])?;
+        require_keys_eq!(mint_in_account.key(), mint_in, AmmError::InvalidToken);
+        require_keys_eq!(mint_out_account.key(), mint_out, AmmError::InvalidToken);
+        let program_in = self.token_program_for(&hop[1])?;
+        let program_out = self.token_program_for(&hop[2])?;
+
+        let mut vault_in = InterfaceAccount::<TokenAccount>::try_from(&hop[3])?;
+        let mut vault_out = InterfaceAccount::<TokenAccount>::try_from(&hop[4])?;
+        require_keys_eq!(
+            vault_in.key(),
+            get_associated_token_address_with_program_id(&config.key(), &mint_in, hop[1].owner),
+            AmmError::InvalidRoute
+        );
+        require_keys_eq!(
+            vault_out.key(),
+            get_associated_token_address_with_program_id(&config.key(), &mint_out, hop[2].owner),
+            AmmError::InvalidRoute
+        );
+
+        let treasury_bump = match is_x {
+            true => config.treasury_x_bump,
+            false => config.treasury_y_bump,
+        };
+        require_keys_eq!(
+            hop[5].key(),
+            Pubkey::create_program_address(
+                &[b"treasury", config.key().as_ref(), mint_in.as_ref(), &[treasury_bump]],
+                &crate::ID,
+            )
+            .map_err(|_| AmmError::InvalidRoute)?,
+            AmmError::InvalidRoute
+        );
+
+        let mut oracle = Account::<Oracle>::try_from(&hop[6])?;
+        require_keys_eq!(
+            oracle.key(),
+            Pubkey::create_program_address(
+                &[b"oracle", config.key().as_ref(), &[config.oracle_bump]],
+                &crate::ID,
+            )
+            .map_err(|_| AmmError::InvalidRoute)?,
+            AmmError::InvalidRoute
+        );
+
+        let user_out = InterfaceAccount::<TokenAccount>::try_from(&hop[7])?;
+        require_keys_eq!(user_out.mint, mint_out, AmmError::InvalidToken);
+        require_keys_eq!(user_out.owner, self.user.key(), AmmError::InvalidRoute);
+
+        let (reserve_x, reserve_y) = match is_x {
+            true => (vault_in.amount, vault_out.amount),
+            false => (vault_out.amount, vault_in.amount),
+        };
+
+        // what reaches vault_in
+        let received = amount
+            .checked_sub(transfer_fee(&hop[1], amount)?)
+            .ok_or(AmmError::Underflow)?;
+        let res = config.quote_exact_in(reserve_x, reserve_y, is_x, received, now)?;
+
+        let seeds = &[
+            &b"config"[..],
+            &config.seed.to_le_bytes(),
+            &[config.config_bump],
+        ];
+        let signer_seeds = &[&seeds[..]];
+        transfer_tokens(
+            source.to_account_info(),
+            hop[3].to_account_info(),
+            &mint_in_account,
+            self.user.to_account_info(),
+            program_in.clone(),
+            amount,
+            &[],
+        )?;
+        let protocol_fee = config.protocol_fee_amount(res.fee)?;
+        if protocol_fee > 0 {
+            transfer_tokens(
+                hop[3].to_account_info(),
+                hop[5].to_account_info(),
+                &mint_in_account,
+                config.to_account_info(),
+                program_in,
+                protocol_fee,
+                signer_seeds,
+            )?;
+        }
+        transfer_tokens(
+            hop[4].to_account_info(),
+            hop[7].to_account_info(),
+            &mint_out_account,
+            config.to_account_info(),
+            program_out,
+            res.withdraw,
+            signer_seeds,
+        )?;
+
+        let before = (reserve_x, reserve_y);
+        vault_in.reload()?;
+        vault_out.reload()?;
+        let (reserve_x, reserve_y) = match is_x {
+            true => (vault_in.amount, vault_out.amount),
+            false => (vault_out.amount, vault_in.amount),
+        };
+        oracle.update(&config, now, reserve_x, reserve_y);
+        oracle.exit(&crate::ID)?;
+        let fee_bps = config.swap_fee();
+        config.record_price_move(before, (reserve_x, reserve_y), now);
+        config.exit(&crate::ID)?;
+        emit!(Swapped {
+            config: config.key(),
+            user: self.user.key(),
+            is_x,
+            amount_in: amount,
+            amount_out: res.withdraw,
+            fee: res.fee,
+            fee_bps,
+            protocol_fee,
+            reserve_x,
+            reserve_y,
+        });
+
+        let received_out = res
+            .withdraw
+            .checked_sub(transfer_fee(&hop[2], res.withdraw)?)
+            .ok_or(AmmError::Underflow)?;
+        Ok((received_out, mint_out))
+    }
+
+    pub fn token_program_for(&self, mint: &AccountInfo<'info>) -> Result<AccountInfo<'info>> {
+        match *mint.owner {
+            id if id == self.token_program.key() => Ok(self.token_program.to_account_info()),
+            id if id == self.token_program_2022.key() => {
+                Ok(self.token_program_2022.to_account_info())
+            }
+            _ => err!(AmmError::InvalidToken),
+        }
+    }
+}
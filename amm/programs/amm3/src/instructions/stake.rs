@@ -0,0 +1,193 @@
+use crate::error::AmmError;
+use crate::events::RewardsClaimed;
+use crate::state::{Config, Farm, UserStake};
+use crate::utils::transfer_tokens;
+use anchor_lang::prelude::*;
+use anchor_spl::{
+    associated_token::AssociatedToken,
+    token_interface::{Mint, TokenAccount, TokenInterface},
+};
+
+#[derive(Accounts)]
+pub struct Stake<'info> {
+    #[account(mut)]
+    pub user: Signer<'info>,
+    #[account(
+        seeds= [b"config", config.seed.to_le_bytes().as_ref()],
+        bump= config.config_bump,
+    )]
+    pub config: Box<Account<'info, Config>>,
+    #[account(
+        seeds= [b"lp", config.key().as_ref()],
+        bump= config.lp_bump,
+        mint::token_program = token_program,
+    )]
+    pub mint_lp: Box<InterfaceAccount<'info, Mint>>,
+    #[account(
+        mint::token_program = reward_token_program,
+    )]
+    pub reward_mint: Box<InterfaceAccount<'info, Mint>>,
+    #[account(
+        mut,
+        has_one = config,
+        has_one = reward_mint,
+        seeds = [b"farm", config.key().as_ref()],
+        bump = farm.bump,
+    )]
+    pub farm: Box<Account<'info, Farm>>,
+    #[account(
+        init_if_needed,
+        payer = user,
+        seeds = [b"stake", farm.key().as_ref(), user.key().as_ref()],
+        bump,
+        space = 8 + UserStake::INIT_SPACE,
+    )]
+    pub user_stake: Box<Account<'info, UserStake>>,
+    #[account(
+        mut,
+        associated_token::mint= mint_lp,
+        associated_token::authority= farm,
+        associated_token::token_program= token_program,
+    )]
+    pub stake_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+    #[account(
+        mut,
+        associated_token::mint= reward_mint,
+        associated_token::authority= farm,
+        associated_token::token_program= reward_token_program,
+    )]
+    pub reward_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+    #[account(
+        init_if_needed,
+        payer = user,
+        associated_token::mint= mint_lp,
+        associated_token::authority= user,
+        associated_token::token_program= token_program,
+    )]
+    pub user_lp: Box<InterfaceAccount<'info, TokenAccount>>,
+    #[account(
+        init_if_needed,
+        payer = user,
+        associated_token::mint= reward_mint,
+        associated_token::authority= user,
+        associated_token::token_program= reward_token_program,
+    )]
+    pub user_reward: Box<InterfaceAccount<'info, TokenAccount>>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub reward_token_program: Interface<'info, TokenInterface>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+}
+
+impl<'info> Stake<'info> {
+    // a pool being closed takes no new stakes, so its farm can be wound down
+    pub fn stake_lp(&mut self, amount: u64, bumps: StakeBumps) -> Result<()> {
+        require!(amount > 0, AmmError::InvalidAmount);
+        self.config.check_not_closing()?;
+        if self.user_stake.owner == Pubkey::default() {
+            self.user_stake.farm = self.farm.key();
+            self.user_stake.owner = self.user.key();
+            self.user_stake.bump = bumps.user_stake;
+            self.farm.stakers = self
+                .farm
+                .stakers
+                .checked_add(1)
+                .ok_or(AmmError::Overflow)?;
+        }
+        self.settle()?;
+        transfer_tokens(
+            self.user_lp.to_account_info(),
+            self.stake_vault.to_account_info(),
+            &self.mint_lp,
+            self.user.to_account_info(),
+            self.token_program.to_account_info(),
+            amount,
+            &[],
+        )?;
+        let staked = self
+            .user_stake
+            .amount
+            .checked_add(amount)
+            .ok_or(AmmError::Overflow)?;
+        self.user_stake
+            .set_amount(staked, self.farm.reward_per_share);
+        self.farm.total_staked = self
+            .farm
+            .total_staked
+            .checked_add(amount)
+            .ok_or(AmmError::Overflow)?;
+        Ok(())
+    }
+
+    // rewards earned so far stay pending until claimed
+    pub fn unstake_lp(&mut self, amount: u64) -> Result<()> {
+        require!(amount > 0, AmmError::InvalidAmount);
+        require!(
+            amount <= self.user_stake.amount,
+            AmmError::Insufficientbalance
+        );
+        // if the farm cannot accrue the stake settles at the last accrued rate, so reward
+        // accounting never traps staked LP
+        let _ = self.farm.accrue(Clock::get()?.unix_timestamp);
+        self.user_stake.settle(self.farm.reward_per_share);
+        self.withdraw_from_farm(false, amount)?;
+        let staked = self.user_stake.amount - amount;
+        self.user_stake
+            .set_amount(staked, self.farm.reward_per_share);
+        self.farm.total_staked -= amount;
+        Ok(())
+    }
+
+    pub fn claim(&mut self) -> Result<()> {
+        self.settle()?;
+        let amount = self.user_stake.pending;
+        if amount == 0 {
+            return Ok(());
+        }
+        self.user_stake.pending = 0;
+        self.withdraw_from_farm(true, amount)?;
+        emit!(RewardsClaimed {
+            config: self.config.key(),
+            farm: self.farm.key(),
+            user: self.user.key(),
+            amount,
+        });
+        Ok(())
+    }
+
+    pub fn settle(&mut self) -> Result<()> {
+        self.farm.accrue(Clock::get()?.unix_timestamp)?;
+        self.user_stake.settle(self.farm.reward_per_share);
+        Ok(())
+    }
+
+    // pays rewards, or returns staked LP, out of the farm's vaults
+    pub fn withdraw_from_farm(&self, is_reward: bool, amount: u64) -> Result<()> {
+        let (from, to, mint, token_program) = match is_reward {
+            true => (
+                self.reward_vault.to_account_info(),
+                self.user_reward.to_account_info(),
+                &self.reward_mint,
+                self.reward_token_program.to_account_info(),
+            ),
+            false => (
+                self.stake_vault.to_account_info(),
+                self.user_lp.to_account_info(),
+                &self.mint_lp,
+                self.token_program.to_account_info(),
+            ),
+        };
+        let config = self.config.key();
+        let seeds = &[&b"farm"[..], config.as_ref(), &[self.farm.bump]];
+        let signer_seeds = &[&seeds[..]];
+        transfer_tokens(
+            from,
+            to,
+            mint,
+            self.farm.to_account_info(),
+            token_program,
+            amount,
+            signer_seeds,
+        )
+    }
+}
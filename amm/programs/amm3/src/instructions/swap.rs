@@ -1,118 +1,273 @@
 use anchor_lang::prelude::*;
 use anchor_spl::{
     associated_token::AssociatedToken,
-    token::{mint_to, transfer, Mint, MintTo, Token, TokenAccount, Transfer},
+    token_interface::{Mint, TokenAccount, TokenInterface},
 };
-use constant_product_curve::{ConstantProduct, LiquidityPair};
 
-use crate::{error::AmmError, state::Config};
+use crate::{
+    error::AmmError,
+    events::{ReferralPaid, Swapped},
+    state::{AllowlistEntry, Config, Oracle},
+    utils::{check_expiry, inverse_transfer_fee, is_native_mint, transfer_fee, transfer_tokens, unwrap_native, wrap_native},
+};
 
 #[derive(Accounts)]
 pub struct Swap<'info> {
     #[account(mut)]
     pub user: Signer<'info>,
-    pub mint_x: Account<'info, Mint>,
-    pub mint_y: Account<'info, Mint>,
+    #[account(
+        mint::token_program= token_program_x,
+    )]
+    pub mint_x: Box<InterfaceAccount<'info, Mint>>,
+    #[account(
+        mint::token_program= token_program_y,
+    )]
+    pub mint_y: Box<InterfaceAccount<'info, Mint>>,
+    // left out for the wSOL side in native SOL mode
     #[account(
         init_if_needed,
         payer=user,
         associated_token::mint= mint_x,
         associated_token::authority= user,
+        associated_token::token_program= token_program_x,
     )]
-    pub user_x: Account<'info, TokenAccount>,
+    pub user_x: Option<Box<InterfaceAccount<'info, TokenAccount>>>,
     #[account(
         init_if_needed,
         payer=user,
         associated_token::mint= mint_y,
         associated_token::authority=user,
+        associated_token::token_program= token_program_y,
     )]
-    pub user_y: Account<'info, TokenAccount>,
+    pub user_y: Option<Box<InterfaceAccount<'info, TokenAccount>>>,
     #[account(
         mut, 
         associated_token::mint= mint_x,
         associated_token::authority= config,
+        associated_token::token_program= token_program_x,
     )]
-    pub vault_x: Account<'info, TokenAccount>,
+    pub vault_x: Box<InterfaceAccount<'info, TokenAccount>>,
     #[account(
         mut,
         associated_token::mint= mint_y,
         associated_token::authority= config,
+        associated_token::token_program= token_program_y,
+    )]
+    pub vault_y: Box<InterfaceAccount<'info,TokenAccount>>,
+    #[account(
+        mut,
+        seeds= [b"treasury", config.key().as_ref(), mint_x.key().as_ref()],
+        bump= config.treasury_x_bump,
+    )]
+    pub treasury_x: Box<InterfaceAccount<'info, TokenAccount>>,
+    #[account(
+        mut,
+        seeds= [b"treasury", config.key().as_ref(), mint_y.key().as_ref()],
+        bump= config.treasury_y_bump,
     )]
-    pub vault_y: Account<'info,TokenAccount>,
+    pub treasury_y: Box<InterfaceAccount<'info, TokenAccount>>,
     #[account(
+        mut,
         has_one= mint_x,
         has_one= mint_y,
         seeds= [b"config", config.seed.to_le_bytes().as_ref()],
         bump= config.config_bump,
     )]
-    pub config: Account<'info, Config>,
-     #[account(
-        seeds=[b"lp",config.key().as_ref()],
-        bump=config.lp_bump,
+    pub config: Box<Account<'info, Config>>,
+    #[account(
+        mut,
+        seeds= [b"oracle", config.key().as_ref()],
+        bump= config.oracle_bump,
     )]
-    pub mint_lp: Account<'info, Mint>,
-    pub token_program: Program<'info, Token>,
+    pub oracle: Box<Account<'info, Oracle>>,
+    pub token_program_x: Interface<'info, TokenInterface>,
+    pub token_program_y: Interface<'info, TokenInterface>,
     pub associated_token_program: Program<'info, AssociatedToken>,
     pub system_program: Program<'info, System>,
+    // front-end's token account for the input mint, paid `referral_bps` of the swap fee
+    #[account(mut)]
+    pub referrer: Option<Box<InterfaceAccount<'info, TokenAccount>>>,
+    // the user's entry, needed only while the pool is in allowlist mode
+    pub allowlist_entry: Option<Box<Account<'info, AllowlistEntry>>>,
+    // native SOL mode only: stands in for the user's wSOL account within the instruction
+    /// CHECK: created by `wrap_native` and closed by `unwrap_native`, pinned by its seeds
+    #[account(
+        mut,
+        seeds= [b"wsol", user.key().as_ref()],
+        bump,
+    )]
+    pub native_account: Option<UncheckedAccount<'info>>,
 }
 
 impl <'info> Swap<'info>{
-    pub fn swap(&mut self, is_x: bool, amount: u64,min: u64)->Result<()>{
-        require!(self.config.locked==false, AmmError::PoolLocked);
+    pub fn swap(&mut self, is_x: bool, amount: u64,min: u64, expires_at: i64, native_sol: bool, referral_bps: u16)->Result<()>{
+        let now = check_expiry(expires_at)?;
+        self.config.check_unlocked()?;
+        self.config.check_allowlisted(&self.config.key(), &self.user.key(), self.allowlist_entry.as_deref())?;
         require!(amount> 0, AmmError::InvalidAmount);
-        
-        let mut curve= ConstantProduct::init(
+        self.config.refresh_dynamic_fee(Clock::get()?.slot);
+
+        let (mint_in, mint_out) = self.mints(is_x);
+        // both legs net of transfer fees
+        let received = amount.checked_sub(transfer_fee(&mint_in, amount)?).ok_or(AmmError::Underflow)?;
+        let res = self.config.quote_exact_in(
             self.vault_x.amount,
             self.vault_y.amount,
-            self.mint_lp.supply,
-            self.config.fee,
-            None,
-            ).map_err(AmmError::from)?;
-        
-        let p=match is_x{
-            true=>LiquidityPair::X,
-            false=> LiquidityPair::Y,
+            is_x,
+            received,
+            now,
+        )?;
+        let amount_out = res.withdraw.checked_sub(transfer_fee(&mint_out, res.withdraw)?).ok_or(AmmError::Underflow)?;
+        require!(amount_out>= min, AmmError::SlippageExceded);
+
+        self.settle(is_x, amount, res.fee, res.withdraw, native_sol, referral_bps, now)
+    }
+
+    pub fn swap_exact_out(&mut self, is_x: bool, amount_out: u64, max_in: u64, expires_at: i64, native_sol: bool, referral_bps: u16)->Result<()>{
+        let now = check_expiry(expires_at)?;
+        self.config.check_unlocked()?;
+        self.config.check_allowlisted(&self.config.key(), &self.user.key(), self.allowlist_entry.as_deref())?;
+        require!(amount_out> 0, AmmError::InvalidAmount);
+        self.config.refresh_dynamic_fee(Clock::get()?.slot);
+
+        let (reserve_in, reserve_out) = match is_x {
+            true=>(self.vault_x.amount, self.vault_y.amount),
+            false=>(self.vault_y.amount, self.vault_x.amount),
         };
+        let (mint_in, mint_out) = self.mints(is_x);
+        // gross up both legs so the user receives exactly `amount_out` after transfer fees
+        let withdraw = amount_out.checked_add(inverse_transfer_fee(&mint_out, amount_out)?).ok_or(AmmError::Overflow)?;
+        let (received, fee) = self.config.quote_exact_out(reserve_in, reserve_out, is_x, withdraw, now)?;
+        let amount_in = received.checked_add(inverse_transfer_fee(&mint_in, received)?).ok_or(AmmError::Overflow)?;
+        require!(amount_in<= max_in, AmmError::SlippageExceded);
+
+        self.settle(is_x, amount_in, fee, withdraw, native_sol, referral_bps, now)
+    }
+
+    pub fn mints(&self, is_x: bool)->(AccountInfo<'info>, AccountInfo<'info>){
+        match is_x {
+            true=>(self.mint_x.to_account_info(), self.mint_y.to_account_info()),
+            false=>(self.mint_y.to_account_info(), self.mint_x.to_account_info()),
+        }
+    }
+
+    // native SOL mode moves the wSOL side through the temporary account, see `wrap_native`
+    #[allow(clippy::too_many_arguments)]
+    pub fn settle(&mut self, is_x: bool, amount_in: u64, fee: u64, amount_out: u64, native_sol: bool, referral_bps: u16, now: i64)->Result<()>{
+        require!(native_sol == self.native_account.is_some(), AmmError::NativeAccountMismatch);
+        if let Some(native_account) = self.native_account.as_ref() {
+            self.config.check_native()?;
+            let native_is_x = is_native_mint(&self.mint_x.key());
+            let (mint, program) = self.native_side();
+            let wrapped = match native_is_x == is_x {
+                true=>amount_in,
+                false=>0,
+            };
+            wrap_native(mint, native_account.to_account_info(), self.user.to_account_info(), self.system_program.to_account_info(), program, wrapped)?;
+        }
+        self.deposit_tokens(is_x, amount_in)?;
 
-        let res= curve.swap(p, amount, min).map_err(AmmError::from)?;
+        let protocol_fee = self.config.protocol_fee_amount(fee)?;
+        if protocol_fee > 0 {
+            self.withdraw_tokens(is_x, protocol_fee, true)?;
+        }
+        self.pay_referrer(is_x, fee, referral_bps)?;
 
-        require!(res.deposit!=0, AmmError::InvalidAmount);
-        require!(res.withdraw!=0,AmmError::InvalidAmount);
+        self.withdraw_tokens(!is_x, amount_out, false)?;
 
-    
-        self.deposit_tokens(is_x,res.deposit)?;
-   
-        self.withdraw_tokens(is_x,res.withdraw)?;
-       
+        let before = (self.vault_x.amount, self.vault_y.amount);
+        self.vault_x.reload()?;
+        self.vault_y.reload()?;
+        self.oracle.update(&self.config, now, self.vault_x.amount, self.vault_y.amount);
+        let fee_bps = self.config.swap_fee();
+        self.config.record_price_move(before, (self.vault_x.amount, self.vault_y.amount), now);
+        emit!(Swapped{
+            config: self.config.key(),
+            user: self.user.key(),
+            is_x,
+            amount_in,
+            amount_out,
+            fee,
+            fee_bps,
+            protocol_fee,
+            reserve_x: self.vault_x.amount,
+            reserve_y: self.vault_y.amount,
+        });
+
+        if let Some(native_account) = self.native_account.as_ref() {
+            let (_, program) = self.native_side();
+            unwrap_native(native_account.to_account_info(), self.user.to_account_info(), program)?;
+        }
         Ok(())
     }
 
-    pub fn deposit_tokens(&mut self, is_x:bool, amount: u64)-> Result<()>{
-        let (from, to) = match is_x {
-            true=>(self.user_x.to_account_info(), self.vault_x.to_account_info()),
-            false=> (self.user_y.to_account_info(), self.vault_y.to_account_info()),
+    pub fn native_side(&self)->(&InterfaceAccount<'info, Mint>, AccountInfo<'info>){
+        match is_native_mint(&self.mint_x.key()) {
+            true=>(&self.mint_x, self.token_program_x.to_account_info()),
+            false=>(&self.mint_y, self.token_program_y.to_account_info()),
+        }
+    }
+
+    // the user's token account for a side, the temporary one for the wSOL side in native SOL mode
+    pub fn user_account(&self, is_x: bool)->Result<AccountInfo<'info>>{
+        let (mint, account) = match is_x {
+            true=>(&self.mint_x, self.user_x.as_ref()),
+            false=>(&self.mint_y, self.user_y.as_ref()),
+        };
+        match (self.native_account.as_ref(), account) {
+            (Some(native_account), _) if is_native_mint(&mint.key())=>Ok(native_account.to_account_info()),
+            (_, Some(account))=>Ok(account.to_account_info()),
+            (_, None)=>err!(AmmError::MissingTokenAccount),
+        }
+    }
+
+    // the referrer's cut comes out of the LP share of the fee, in the input token
+    pub fn pay_referrer(&self, is_x: bool, fee: u64, referral_bps: u16)->Result<()>{
+        let Some(referrer) = self.referrer.as_ref() else {
+            return Ok(());
         };
-        let cpi_program =self.token_program.to_account_info();
-        let accounts= Transfer{
-            from: from.to_account_info(),
-            to: to.to_account_info(),
-            authority: self.user.to_account_info(),
+        require_keys_neq!(referrer.owner, self.user.key(), AmmError::SelfReferral);
+        let amount = self.config.referral_fee_amount(fee, referral_bps)?;
+        if amount == 0 {
+            return Ok(());
+        }
+        let (vault, mint, program) = match is_x {
+            true=>(self.vault_x.to_account_info(), &self.mint_x, self.token_program_x.to_account_info()),
+            false=>(self.vault_y.to_account_info(), &self.mint_y, self.token_program_y.to_account_info()),
         };
-        let cpi_ctx = CpiContext::new(cpi_program, accounts);
-        transfer(cpi_ctx, amount)?;
+        require_keys_eq!(referrer.mint, mint.key(), AmmError::InvalidToken);
+        let seeds= &[
+            &b"config"[..],
+            &self.config.seed.to_le_bytes(),
+            &[self.config.config_bump],
+        ];
+        transfer_tokens(vault, referrer.to_account_info(), mint, self.config.to_account_info(), program, amount, &[&seeds[..]])?;
+        emit!(ReferralPaid{
+            config: self.config.key(),
+            user: self.user.key(),
+            referrer: referrer.key(),
+            mint: mint.key(),
+            amount,
+        });
         Ok(())
     }
-    pub fn withdraw_tokens(&mut self,is_x:bool, amount:u64)->Result<()>{
-        let (from, to) = match is_x {
-            true=>(self.vault_x.to_account_info(), self.user_x.to_account_info()),
-            false=> (self.vault_y.to_account_info(), self.user_y.to_account_info()),
+
+    pub fn deposit_tokens(&mut self, is_x:bool, amount: u64)-> Result<()>{
+        let (to, mint, program) = match is_x {
+            true=>(self.vault_x.to_account_info(), &self.mint_x, self.token_program_x.to_account_info()),
+            false=> (self.vault_y.to_account_info(), &self.mint_y, self.token_program_y.to_account_info()),
         };
-        let cpi_program =self.token_program.to_account_info();
+        let from = self.user_account(is_x)?;
+        transfer_tokens(from, to, mint, self.user.to_account_info(), program, amount, &[])
+    }
 
-        let accounts= Transfer{
-            from: from.to_account_info(),
-            to: to.to_account_info(),
-            authority: self.config.to_account_info(),
+    // pays `amount` of x (or y) out of the vault, to the user or to the treasury
+    pub fn withdraw_tokens(&mut self, is_x:bool, amount:u64, to_treasury: bool)->Result<()>{
+        let (from, to, mint, program) = match (is_x, to_treasury) {
+            (true, false)=>(self.vault_x.to_account_info(), self.user_account(true)?, &self.mint_x, self.token_program_x.to_account_info()),
+            (false, false)=> (self.vault_y.to_account_info(), self.user_account(false)?, &self.mint_y, self.token_program_y.to_account_info()),
+            (true, true)=>(self.vault_x.to_account_info(), self.treasury_x.to_account_info(), &self.mint_x, self.token_program_x.to_account_info()),
+            (false, true)=> (self.vault_y.to_account_info(), self.treasury_y.to_account_info(), &self.mint_y, self.token_program_y.to_account_info()),
         };
         let seeds= &[
             &b"config"[..],
@@ -120,8 +275,6 @@ impl <'info> Swap<'info>{
             &[self.config.config_bump],
         ];
         let signer_seeds =&[&seeds[..]];
-        let cpi_ctx= CpiContext::new_with_signer(cpi_program, accounts, signer_seeds);
-        transfer(cpi_ctx, amount)?;
-        Ok(())
+        transfer_tokens(from, to, mint, self.config.to_account_info(), program, amount, signer_seeds)
     }
 }
@@ -0,0 +1,270 @@
+use crate::constants::{
+    FORCED_EXIT_WINDOW, MAX_AMP, MAX_AMP_CHANGE, MAX_BPS, MIN_AMP, MIN_RAMP_DURATION,
+};
+use crate::error::AmmError;
+use crate::events::{
+    AllowlistUpdated, AmpRampUpdated, AuthorityUpdated, DynamicFeeUpdated, FeeUpdated,
+    FlashFeeUpdated, ForcedExitStarted, LockUpdated, ProtocolFeeUpdated, ReferralFeeUpdated,
+};
+use crate::state::{Config, DynamicFee, FeeTiers, Pair, PoolType};
+use anchor_lang::prelude::*;
+
+#[derive(Accounts)]
+pub struct Update<'info> {
+    pub authority: Signer<'info>,
+    #[account(
+        mut,
+        seeds= [b"config", config.seed.to_le_bytes().as_ref()],
+        bump= config.config_bump,
+    )]
+    pub config: Account<'info, Config>,
+}
+
+// the pool's pair entry is keyed by its fee, so a new fee moves it to the new tier's address
+#[derive(Accounts)]
+#[instruction(fee: u16)]
+pub struct UpdateFee<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    /// CHECK: only gets the old pair entry's rent back, pinned by `has_one` on the config
+    #[account(mut)]
+    pub initializer: UncheckedAccount<'info>,
+    #[account(
+        mut,
+        has_one = initializer,
+        seeds= [b"config", config.seed.to_le_bytes().as_ref()],
+        bump= config.config_bump,
+    )]
+    pub config: Box<Account<'info, Config>>,
+    #[account(
+        seeds = [b"fee_tiers"],
+        bump = fee_tiers.bump,
+    )]
+    pub fee_tiers: Box<Account<'info, FeeTiers>>,
+    #[account(
+        mut,
+        close = initializer,
+        has_one = config,
+        seeds = [b"pair", config.mint_x.as_ref(), config.mint_y.as_ref(), pair.fee.to_le_bytes().as_ref()],
+        bump = pair.bump,
+    )]
+    pub pair: Box<Account<'info, Pair>>,
+    // fails if the pair already has a pool at the new tier
+    #[account(
+        init,
+        payer = authority,
+        seeds = [b"pair", config.mint_x.as_ref(), config.mint_y.as_ref(), fee.to_le_bytes().as_ref()],
+        bump,
+        space = 8 + Pair::INIT_SPACE,
+    )]
+    pub new_pair: Box<Account<'info, Pair>>,
+    pub system_program: Program<'info, System>,
+}
+
+impl<'info> UpdateFee<'info> {
+    pub fn update_fee(&mut self, fee: u16, bumps: UpdateFeeBumps) -> Result<()> {
+        self.config.check_authority(&self.authority.key())?;
+        // in dynamic mode the tier fee is the floor the accumulator works from
+        require!(!self.config.dynamic_fee.enabled, AmmError::InvalidFee);
+        self.fee_tiers.check_fee(fee)?;
+        let old_fee = self.config.fee;
+        self.config.fee = fee;
+        self.new_pair.set_inner(Pair {
+            config: self.config.key(),
+            mint_x: self.config.mint_x,
+            mint_y: self.config.mint_y,
+            fee,
+            bump: bumps.new_pair,
+        });
+        emit!(FeeUpdated {
+            config: self.config.key(),
+            old_fee,
+            new_fee: fee,
+        });
+        Ok(())
+    }
+}
+
+impl<'info> Update<'info> {
+    pub fn update_protocol_fee(&mut self, protocol_fee: u16) -> Result<()> {
+        self.config.check_authority(&self.authority.key())?;
+        // the treasury and referrer shares together cannot exceed the whole fee
+        require!(
+            protocol_fee <= MAX_BPS - self.config.referral_fee,
+            AmmError::InvalidFee
+        );
+        let old_protocol_fee = self.config.protocol_fee;
+        self.config.protocol_fee = protocol_fee;
+        emit!(ProtocolFeeUpdated {
+            config: self.config.key(),
+            old_protocol_fee,
+            new_protocol_fee: protocol_fee,
+        });
+        Ok(())
+    }
+
+    pub fn update_referral_fee(&mut self, referral_fee: u16) -> Result<()> {
+        self.config.check_authority(&self.authority.key())?;
+        require!(
+            referral_fee <= MAX_BPS - self.config.protocol_fee,
+            AmmError::InvalidFee
+        );
+        let old_referral_fee = self.config.referral_fee;
+        self.config.referral_fee = referral_fee;
+        emit!(ReferralFeeUpdated {
+            config: self.config.key(),
+            old_referral_fee,
+            new_referral_fee: referral_fee,
+        });
+        Ok(())
+    }
+
+    pub fn update_flash_fee(&mut self, flash_fee: u16) -> Result<()> {
+        self.config.check_authority(&self.authority.key())?;
+        require!(flash_fee < MAX_BPS, AmmError::InvalidFee);
+        let old_flash_fee = self.config.flash_fee;
+        self.config.flash_fee = flash_fee;
+        emit!(FlashFeeUpdated {
+            config: self.config.key(),
+            old_flash_fee,
+            new_flash_fee: flash_fee,
+        });
+        Ok(())
+    }
+
+    // the pool's tier fee stays the floor, volatility only lifts the fee up to `max_fee`
+    pub fn enable_dynamic_fee(&mut self, max_fee: u16) -> Result<()> {
+        self.config.check_authority(&self.authority.key())?;
+        let min_fee = self.config.fee;
+        require!(
+            min_fee <= max_fee && max_fee < MAX_BPS,
+            AmmError::InvalidFee
+        );
+        self.config.dynamic_fee = DynamicFee {
+            enabled: true,
+            max_fee,
+            volatility: 0,
+            last_slot: Clock::get()?.slot,
+            fee: min_fee,
+        };
+        emit!(DynamicFeeUpdated {
+            config: self.config.key(),
+            enabled: true,
+            min_fee,
+            max_fee,
+        });
+        Ok(())
+    }
+
+    // the pool goes back to charging its tier fee
+    pub fn disable_dynamic_fee(&mut self) -> Result<()> {
+        self.config.check_authority(&self.authority.key())?;
+        require!(self.config.dynamic_fee.enabled, AmmError::InvalidFee);
+        self.config.dynamic_fee = DynamicFee::default();
+        let min_fee = self.config.fee;
+        emit!(DynamicFeeUpdated {
+            config: self.config.key(),
+            enabled: false,
+            min_fee,
+            max_fee: min_fee,
+        });
+        Ok(())
+    }
+
+    pub fn ramp_amp(&mut self, target_amp: u64, ramp_end: i64) -> Result<()> {
+        self.config.check_authority(&self.authority.key())?;
+        require!(
+            self.config.pool_type == PoolType::StableSwap,
+            AmmError::InvalidPoolType
+        );
+        let now = Clock::get()?.unix_timestamp;
+        require!(
+            (MIN_AMP..=MAX_AMP).contains(&target_amp),
+            AmmError::InvalidAmp
+        );
+        require!(
+            ramp_end >= now.saturating_add(MIN_RAMP_DURATION),
+            AmmError::InvalidAmp
+        );
+        // a single ramp may move the coefficient at most MAX_AMP_CHANGE times up or down
+        let current = self.config.amp(now);
+        require!(
+            target_amp <= current.saturating_mul(MAX_AMP_CHANGE)
+                && current <= target_amp.saturating_mul(MAX_AMP_CHANGE),
+            AmmError::InvalidAmp
+        );
+        self.set_amp_ramp(current, target_amp, now, ramp_end)
+    }
+
+    pub fn stop_ramp_amp(&mut self) -> Result<()> {
+        self.config.check_authority(&self.authority.key())?;
+        require!(
+            self.config.pool_type == PoolType::StableSwap,
+            AmmError::InvalidPoolType
+        );
+        let now = Clock::get()?.unix_timestamp;
+        let current = self.config.amp(now);
+        self.set_amp_ramp(current, current, now, now)
+    }
+
+    fn set_amp_ramp(&mut self, from: u64, to: u64, start: i64, end: i64) -> Result<()> {
+        self.config.amp_initial = from;
+        self.config.amp_target = to;
+        self.config.amp_ramp_start = start;
+        self.config.amp_ramp_end = end;
+        emit!(AmpRampUpdated {
+            config: self.config.key(),
+            amp_initial: from,
+            amp_target: to,
+            ramp_start: start,
+            ramp_end: end,
+        });
+        Ok(())
+    }
+
+    pub fn set_locked(&mut self, locked: bool) -> Result<()> {
+        self.config.check_authority(&self.authority.key())?;
+        self.config.locked = locked;
+        emit!(LockUpdated {
+            config: self.config.key(),
+            locked,
+        });
+        Ok(())
+    }
+
+    pub fn set_allowlist(&mut self, enabled: bool) -> Result<()> {
+        self.config.check_authority(&self.authority.key())?;
+        self.config.allowlist = enabled;
+        emit!(AllowlistUpdated {
+            config: self.config.key(),
+            enabled,
+        });
+        Ok(())
+    }
+
+    // stops deposits and gives LPs FORCED_EXIT_WINDOW notice to withdraw; once it runs out
+    // `close_pool` leaves any LP still outstanding a pro-rata claim on the vaults, see `redeem`
+    pub fn start_forced_exit(&mut self) -> Result<()> {
+        self.config.check_authority(&self.authority.key())?;
+        self.config.check_not_closing()?;
+        let close_after = Clock::get()?.unix_timestamp.saturating_add(FORCED_EXIT_WINDOW);
+        self.config.close_after = close_after;
+        emit!(ForcedExitStarted {
+            config: self.config.key(),
+            close_after,
+        });
+        Ok(())
+    }
+
+    pub fn set_authority(&mut self, new_authority: Option<Pubkey>) -> Result<()> {
+        self.config.check_authority(&self.authority.key())?;
+        let old_authority = self.config.authority;
+        self.config.authority = new_authority;
+        emit!(AuthorityUpdated {
+            config: self.config.key(),
+            old_authority,
+            new_authority,
+        });
+        Ok(())
+    }
+}
@@ -1,66 +1,99 @@
+use crate::curve::withdraw_amounts;
 use crate::error::AmmError;
-use crate::state::Config;
+use crate::events::LiquidityRemoved;
+use crate::state::{AllowlistEntry, Config, Oracle};
+use crate::utils::{check_expiry, is_native_mint, transfer_fee, transfer_tokens, unwrap_native, wrap_native};
 use anchor_lang::prelude::*;
 use anchor_spl::{
     associated_token::AssociatedToken,
-    token::{burn, transfer, Burn, Mint, MintTo, Token, TokenAccount, Transfer},
+    token_interface::{burn, Burn, Mint, TokenAccount, TokenInterface},
 };
-use constant_product_curve::ConstantProduct;
 
 #[derive(Accounts)]
 pub struct Withdraw<'info> {
     #[account(mut)]
     pub user: Signer<'info>,
-    pub mint_x: Account<'info, Mint>,
-    pub mint_y: Account<'info, Mint>,
+    #[account(
+        mint::token_program = token_program_x,
+    )]
+    pub mint_x: Box<InterfaceAccount<'info, Mint>>,
+    #[account(
+        mint::token_program = token_program_y,
+    )]
+    pub mint_y: Box<InterfaceAccount<'info, Mint>>,
     #[account(
         has_one = mint_x,
         has_one = mint_y,
         seeds= [b"config", config.seed.to_le_bytes().as_ref()],
         bump=config.config_bump,
     )]
-    pub config: Account<'info, Config>,
+    pub config: Box<Account<'info, Config>>,
     #[account(
         mut, 
         seeds= [b"lp", config.key().as_ref()],
         bump= config.lp_bump,
+        mint::token_program = token_program,
     )]
-    pub mint_lp: Account<'info, Mint>,
+    pub mint_lp: Box<InterfaceAccount<'info, Mint>>,
     #[account(
         mut,
         associated_token::mint= mint_x,
         associated_token::authority=config,
+        associated_token::token_program=token_program_x,
     )]
-    pub vault_x: Account<'info, TokenAccount>,
+    pub vault_x: Box<InterfaceAccount<'info, TokenAccount>>,
     #[account(
         mut,
         associated_token::mint= mint_y,
         associated_token::authority=config,
+        associated_token::token_program=token_program_y,
     )]
-    pub vault_y: Account<'info,TokenAccount>,
+    pub vault_y: Box<InterfaceAccount<'info,TokenAccount>>,
+    // left out for the wSOL side in native SOL mode
     #[account(
         init_if_needed,
         payer= user,
         associated_token::mint= mint_x,
         associated_token::authority=user,
+        associated_token::token_program=token_program_x,
     )]
-    pub user_x: Account<'info, TokenAccount>,
+    pub user_x: Option<Box<InterfaceAccount<'info, TokenAccount>>>,
     #[account(
         init_if_needed,
         payer= user,
         associated_token::mint = mint_y,
         associated_token::authority= user,
+        associated_token::token_program=token_program_y,
     )]
-    pub user_y: Account<'info, TokenAccount>,
+    pub user_y: Option<Box<InterfaceAccount<'info, TokenAccount>>>,
     #[account(
         mut,
         associated_token::mint= mint_lp,
         associated_token::authority=user,
+        associated_token::token_program=token_program,
     )]
-    pub user_lp: Account<'info, TokenAccount>,
+    pub user_lp: Box<InterfaceAccount<'info, TokenAccount>>,
+    #[account(
+        mut,
+        seeds= [b"oracle", config.key().as_ref()],
+        bump= config.oracle_bump,
+    )]
+    pub oracle: Box<Account<'info, Oracle>>,
     pub system_program: Program<'info,System>,
-    pub token_program: Program<'info,Token>,
+    pub token_program: Interface<'info,TokenInterface>,
+    pub token_program_x: Interface<'info,TokenInterface>,
+    pub token_program_y: Interface<'info,TokenInterface>,
     pub associated_token_program: Program<'info, AssociatedToken>,
+    // the user's entry, needed only while the pool is in allowlist mode
+    pub allowlist_entry: Option<Box<Account<'info, AllowlistEntry>>>,
+    // native SOL mode only: stands in for the user's wSOL account within the instruction
+    /// CHECK: created by `wrap_native` and closed by `unwrap_native`, pinned by its seeds
+    #[account(
+        mut,
+        seeds= [b"wsol", user.key().as_ref()],
+        bump,
+    )]
+    pub native_account: Option<UncheckedAccount<'info>>,
 }
 
 impl <'info> Withdraw<'info>{
@@ -68,37 +101,74 @@ impl <'info> Withdraw<'info>{
         &mut self, 
         amount:u64,  //amount of lp tokens user wants to burn
         min_x: u64, //min amount of x token user wants to receive
-        min_y: u64 // min amount of y token user wants to receive
+        min_y: u64, // min amount of y token user wants to receive
+        expires_at: i64,
+        native_sol: bool, // unwrap a wSOL side back to lamports
     )->Result<()>{
-        require!(self.config.locked == false, AmmError::PoolLocked);
+        let now = check_expiry(expires_at)?;
+        self.config.check_unlocked()?;
+        self.config.check_allowlisted(&self.config.key(), &self.user.key(), self.allowlist_entry.as_deref())?;
         require!(amount>0, AmmError::InvalidAmount);
         require!(min_x!=0 && min_y!=0, AmmError::InvalidAmount);
-        let amounts = ConstantProduct::xy_withdraw_amounts_from_l(
+        require!(native_sol == self.native_account.is_some(), AmmError::NativeAccountMismatch);
+        if let Some(native_account) = self.native_account.as_ref() {
+            self.config.check_native()?;
+            let (mint, program) = self.native_side();
+            wrap_native(mint, native_account.to_account_info(), self.user.to_account_info(), self.system_program.to_account_info(), program, 0)?;
+        }
+        let (amount_x, amount_y) = withdraw_amounts(
             self.vault_x.amount,
             self.vault_y.amount, 
             self.mint_lp.supply, 
             amount, 
-            6,
-        ).map_err(AmmError::from)?;
+        )?;
 
-        require!(min_x<=amounts.x && min_y<=amounts.y, AmmError::SlippageExceded);
+        // slippage is checked on what actually reaches the user
+        let received_x = amount_x.checked_sub(transfer_fee(&self.mint_x.to_account_info(), amount_x)?).ok_or(AmmError::Underflow)?;
+        let received_y = amount_y.checked_sub(transfer_fee(&self.mint_y.to_account_info(), amount_y)?).ok_or(AmmError::Underflow)?;
+        require!(min_x<=received_x && min_y<=received_y, AmmError::SlippageExceded);
 
-        self.withdraw_tokens(true, amounts.x)?;
-        self.withdraw_tokens(false, amounts.y)?;
+        self.withdraw_tokens(true, amount_x)?;
+        self.withdraw_tokens(false, amount_y)?;
         self.burn_lp_tokens(amount)?;
+
+        self.vault_x.reload()?;
+        self.vault_y.reload()?;
+        self.oracle.update(&self.config, now, self.vault_x.amount, self.vault_y.amount);
+        emit!(LiquidityRemoved{
+            config: self.config.key(),
+            user: self.user.key(),
+            amount_x,
+            amount_y,
+            lp_amount: amount,
+            reserve_x: self.vault_x.amount,
+            reserve_y: self.vault_y.amount,
+        });
+
+        // native SOL mode pays the wSOL side out as lamports
+        if let Some(native_account) = self.native_account.as_ref() {
+            let (_, program) = self.native_side();
+            unwrap_native(native_account.to_account_info(), self.user.to_account_info(), program)?;
+        }
         Ok(())
     }
 
+    pub fn native_side(&self)->(&InterfaceAccount<'info, Mint>, AccountInfo<'info>){
+        match is_native_mint(&self.mint_x.key()) {
+            true=>(&self.mint_x, self.token_program_x.to_account_info()),
+            false=>(&self.mint_y, self.token_program_y.to_account_info()),
+        }
+    }
+
     pub fn withdraw_tokens(&self, is_x:bool, amount:u64,)->Result<()>{
-        let (from, to) = match is_x {
-            true=> (self.vault_x.to_account_info(), self.user_x.to_account_info()),
-            false=>(self.vault_y.to_account_info(), self.user_y.to_account_info())
+        let (from, to, mint, program) = match is_x {
+            true=> (self.vault_x.to_account_info(), self.user_x.as_ref(), &self.mint_x, self.token_program_x.to_account_info()),
+            false=>(self.vault_y.to_account_info(), self.user_y.as_ref(), &self.mint_y, self.token_program_y.to_account_info())
         };
-        let program = self.token_program.to_account_info();
-        let accounts= Transfer{
-            from,
-            to,
-            authority:self.config.to_account_info(),
+        // the temporary wSOL account takes the native side in native SOL mode
+        let to = match self.native_account.as_ref() {
+            Some(native_account) if is_native_mint(&mint.key())=>native_account.to_account_info(),
+            _=>to.ok_or(AmmError::MissingTokenAccount)?.to_account_info(),
         };
         let seeds= &[
             &b"config"[..],
@@ -106,9 +176,7 @@ impl <'info> Withdraw<'info>{
             &[self.config.config_bump],
         ];
         let signer_seeds = &[&seeds[..]];
-        let cpi_ctx= CpiContext::new_with_signer(program, accounts, signer_seeds);
-        transfer(cpi_ctx, amount)?;
-        Ok(())
+        transfer_tokens(from, to, mint, self.config.to_account_info(), program, amount, signer_seeds)
     }
     pub fn burn_lp_tokens(&self, amount:u64)->Result<()>{
         let program= self.token_program.to_account_info();
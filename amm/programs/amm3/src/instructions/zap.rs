@@ -0,0 +1,410 @@
+use crate::error::AmmError;
+use crate::events::{LiquidityAdded, LiquidityRemoved, Swapped};
+use crate::curve::{mul_div, Rounding};
+use crate::state::{AllowlistEntry, Config, Oracle};
+use crate::utils::{check_expiry, inverse_transfer_fee, transfer_fee, transfer_tokens};
+use anchor_lang::prelude::*;
+use anchor_spl::{
+    associated_token::AssociatedToken,
+    token_interface::{burn, mint_to, Burn, Mint, MintTo, TokenAccount, TokenInterface},
+};
+
+#[derive(Accounts)]
+pub struct Zap<'info> {
+    #[account(mut)]
+    pub user: Signer<'info>,
+    #[account(
+        mint::token_program = token_program_x,
+    )]
+    pub mint_x: Box<InterfaceAccount<'info, Mint>>,
+    #[account(
+        mint::token_program = token_program_y,
+    )]
+    pub mint_y: Box<InterfaceAccount<'info, Mint>>,
+    #[account(
+        mut,
+        has_one = mint_x,
+        has_one = mint_y,
+        seeds= [b"config", config.seed.to_le_bytes().as_ref()],
+        bump=config.config_bump,
+    )]
+    pub config: Box<Account<'info, Config>>,
+    #[account(
+        mut,
+        seeds=[b"lp", config.key().as_ref()],
+        bump=config.lp_bump,
+        mint::token_program = token_program,
+    )]
+    pub mint_lp: Box<InterfaceAccount<'info, Mint>>,
+    #[account(
+        mut,
+        associated_token::mint= mint_x,
+        associated_token::authority= config,
+        associated_token::token_program= token_program_x,
+    )]
+    pub vault_x: Box<InterfaceAccount<'info, TokenAccount>>,
+    #[account(
+        mut,
+        associated_token::mint= mint_y,
+        associated_token::authority= config,
+        associated_token::token_program= token_program_y,
+    )]
+    pub vault_y: Box<InterfaceAccount<'info, TokenAccount>>,
+    #[account(
+        mut,
+        seeds= [b"treasury", config.key().as_ref(), mint_x.key().as_ref()],
+        bump= config.treasury_x_bump,
+    )]
+    pub treasury_x: Box<InterfaceAccount<'info, TokenAccount>>,
+    #[account(
+        mut,
+        seeds= [b"treasury", config.key().as_ref(), mint_y.key().as_ref()],
+        bump= config.treasury_y_bump,
+    )]
+    pub treasury_y: Box<InterfaceAccount<'info, TokenAccount>>,
+    #[account(
+        init_if_needed,
+        payer = user,
+        associated_token::mint= mint_x,
+        associated_token::authority= user,
+        associated_token::token_program= token_program_x,
+    )]
+    pub user_x: Box<InterfaceAccount<'info, TokenAccount>>,
+    #[account(
+        init_if_needed,
+        payer = user,
+        associated_token::mint= mint_y,
+        associated_token::authority= user,
+        associated_token::token_program= token_program_y,
+    )]
+    pub user_y: Box<InterfaceAccount<'info, TokenAccount>>,
+    #[account(
+        init_if_needed,
+        payer = user,
+        associated_token::mint= mint_lp,
+        associated_token::authority= user,
+        associated_token::token_program= token_program,
+    )]
+    pub user_lp: Box<InterfaceAccount<'info, TokenAccount>>,
+    #[account(
+        mut,
+        seeds= [b"oracle", config.key().as_ref()],
+        bump= config.oracle_bump,
+    )]
+    pub oracle: Box<Account<'info, Oracle>>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub token_program_x: Interface<'info, TokenInterface>,
+    pub token_program_y: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    // the user's entry, needed only while the pool is in allowlist mode
+    pub allowlist_entry: Option<Box<Account<'info, AllowlistEntry>>>,
+}
+
+impl<'info> Zap<'info> {
+    // deposits only x (or y): part of it is swapped at the pool's curve and fee, both sides
+    // are deposited and whatever does not fit the pool ratio stays with the user
+    pub fn deposit_single(
+        &mut self,
+        is_x: bool,
+        amount_in: u64,
+        min_lp: u64,
+        expires_at: i64,
+    ) -> Result<()> {
+        let now = check_expiry(expires_at)?;
+        self.config.check_unlocked()?;
+        self.config.check_not_closing()?;
+        self.config.check_allowlisted(
+            &self.config.key(),
+            &self.user.key(),
+            self.allowlist_entry.as_deref(),
+        )?;
+        require!(amount_in != 0, AmmError::InvalidAmount);
+        self.config.refresh_dynamic_fee(Clock::get()?.slot);
+        let supply = self.mint_lp.supply;
+        require!(
+            supply != 0 && self.vault_x.amount != 0 && self.vault_y.amount != 0,
+            AmmError::NoLiquidityInPool
+        );
+
+        let (mint_in, _) = self.mints(is_x);
+        let (reserve_in, reserve_out) = self.reserves(is_x);
+        let received = amount_in
+            .checked_sub(transfer_fee(&mint_in, amount_in)?)
+            .ok_or(AmmError::Underflow)?;
+        let split = self.config.zap_split(reserve_in, reserve_out, received, now)?;
+        let (swapped_out, fee, protocol_fee) = match split {
+            0 => (0, 0, 0),
+            _ => {
+                let res = self.config.quote_exact_in(
+                    self.vault_x.amount,
+                    self.vault_y.amount,
+                    is_x,
+                    split,
+                    now,
+                )?;
+                (res.withdraw, res.fee, self.config.protocol_fee_amount(res.fee)?)
+            }
+        };
+
+        // the swap output never leaves the vault, both sides are priced against the pool
+        // as it stands after the swap
+        let pool_in = reserve_in + split - protocol_fee;
+        let pool_out = reserve_out - swapped_out;
+        let deposit_in = received - split;
+        let lp = mul_div(deposit_in, supply, pool_in, Rounding::Down)?
+            .min(mul_div(swapped_out, supply, pool_out, Rounding::Down)?);
+        require!(lp != 0, AmmError::InvalidAmount);
+        require!(lp >= min_lp, AmmError::SlippageExceded);
+
+        let used_in = mul_div(lp, pool_in, supply, Rounding::Up)?;
+        let used_out = mul_div(lp, pool_out, supply, Rounding::Up)?;
+        // input dust is simply not taken, output dust is paid back from the vault
+        let needed = split + used_in;
+        let charged = needed
+            .checked_add(inverse_transfer_fee(&mint_in, needed)?)
+            .ok_or(AmmError::Overflow)?
+            .min(amount_in);
+
+        self.deposit_tokens(is_x, charged)?;
+        if protocol_fee > 0 {
+            self.withdraw_tokens(is_x, protocol_fee, true)?;
+        }
+        if swapped_out > used_out {
+            self.withdraw_tokens(!is_x, swapped_out - used_out, false)?;
+        }
+        self.mint_lp_tokens(lp)?;
+
+        let before = (self.vault_x.amount, self.vault_y.amount);
+        self.vault_x.reload()?;
+        self.vault_y.reload()?;
+        self.oracle.update(&self.config, now, self.vault_x.amount, self.vault_y.amount);
+        if split > 0 {
+            self.emit_swapped(before, is_x, split, swapped_out, fee, protocol_fee, now);
+        }
+        let (amount_x, amount_y) = match is_x {
+            true => (used_in, used_out),
+            false => (used_out, used_in),
+        };
+        emit!(LiquidityAdded {
+            config: self.config.key(),
+            user: self.user.key(),
+            amount_x,
+            amount_y,
+            lp_amount: lp,
+            reserve_x: self.vault_x.amount,
+            reserve_y: self.vault_y.amount,
+        });
+        Ok(())
+    }
+
+    // burns LP and pays out only x (or y): the other side of the share is swapped back into
+    // the pool as it stands after the share is taken out
+    pub fn withdraw_single(
+        &mut self,
+        is_x: bool,
+        amount: u64,
+        min_out: u64,
+        expires_at: i64,
+    ) -> Result<()> {
+        let now = check_expiry(expires_at)?;
+        self.config.check_unlocked()?;
+        self.config.check_allowlisted(
+            &self.config.key(),
+            &self.user.key(),
+            self.allowlist_entry.as_deref(),
+        )?;
+        require!(amount != 0, AmmError::InvalidAmount);
+        self.config.refresh_dynamic_fee(Clock::get()?.slot);
+        let supply = self.mint_lp.supply;
+        require!(supply != 0, AmmError::NoLiquidityInPool);
+
+        let share_x = mul_div(amount, self.vault_x.amount, supply, Rounding::Down)?;
+        let share_y = mul_div(amount, self.vault_y.amount, supply, Rounding::Down)?;
+        let (share_out, share_in) = match is_x {
+            true => (share_x, share_y),
+            false => (share_y, share_x),
+        };
+        let (swapped_out, fee, protocol_fee) = match share_in {
+            0 => (0, 0, 0),
+            _ => {
+                let res = self.config.quote_exact_in(
+                    self.vault_x.amount - share_x,
+                    self.vault_y.amount - share_y,
+                    !is_x,
+                    share_in,
+                    now,
+                )?;
+                (res.withdraw, res.fee, self.config.protocol_fee_amount(res.fee)?)
+            }
+        };
+        let amount_out = share_out + swapped_out;
+        require!(amount_out != 0, AmmError::InvalidAmount);
+
+        let (mint_out, _) = self.mints(is_x);
+        let received = amount_out
+            .checked_sub(transfer_fee(&mint_out, amount_out)?)
+            .ok_or(AmmError::Underflow)?;
+        require!(received >= min_out, AmmError::SlippageExceded);
+
+        if protocol_fee > 0 {
+            self.withdraw_tokens(!is_x, protocol_fee, true)?;
+        }
+        self.withdraw_tokens(is_x, amount_out, false)?;
+        self.burn_lp_tokens(amount)?;
+
+        let before = (self.vault_x.amount, self.vault_y.amount);
+        self.vault_x.reload()?;
+        self.vault_y.reload()?;
+        self.oracle.update(&self.config, now, self.vault_x.amount, self.vault_y.amount);
+        emit!(LiquidityRemoved {
+            config: self.config.key(),
+            user: self.user.key(),
+            amount_x: share_x,
+            amount_y: share_y,
+            lp_amount: amount,
+            reserve_x: self.vault_x.amount,
+            reserve_y: self.vault_y.amount,
+        });
+        if share_in > 0 {
+            self.emit_swapped(before, !is_x, share_in, swapped_out, fee, protocol_fee, now);
+        }
+        Ok(())
+    }
+
+    // the swap half of a zap, reported like any other swap and counted towards volatility
+    #[allow(clippy::too_many_arguments)]
+    pub fn emit_swapped(
+        &mut self,
+        before: (u64, u64),
+        is_x: bool,
+        amount_in: u64,
+        amount_out: u64,
+        fee: u64,
+        protocol_fee: u64,
+        now: i64,
+    ) {
+        let fee_bps = self.config.swap_fee();
+        let after = (self.vault_x.amount, self.vault_y.amount);
+        self.config.record_price_move(before, after, now);
+        emit!(Swapped {
+            config: self.config.key(),
+            user: self.user.key(),
+            is_x,
+            amount_in,
+            amount_out,
+            fee,
+            fee_bps,
+            protocol_fee,
+            reserve_x: self.vault_x.amount,
+            reserve_y: self.vault_y.amount,
+        });
+    }
+
+    pub fn mints(&self, is_x: bool) -> (AccountInfo<'info>, AccountInfo<'info>) {
+        match is_x {
+            true => (self.mint_x.to_account_info(), self.mint_y.to_account_info()),
+            false => (self.mint_y.to_account_info(), self.mint_x.to_account_info()),
+        }
+    }
+
+    pub fn reserves(&self, is_x: bool) -> (u64, u64) {
+        match is_x {
+            true => (self.vault_x.amount, self.vault_y.amount),
+            false => (self.vault_y.amount, self.vault_x.amount),
+        }
+    }
+
+    pub fn deposit_tokens(&self, is_x: bool, amount: u64) -> Result<()> {
+        let (from, to, mint, token_program) = match is_x {
+            true => (
+                self.user_x.to_account_info(),
+                self.vault_x.to_account_info(),
+                &self.mint_x,
+                self.token_program_x.to_account_info(),
+            ),
+            false => (
+                self.user_y.to_account_info(),
+                self.vault_y.to_account_info(),
+                &self.mint_y,
+                self.token_program_y.to_account_info(),
+            ),
+        };
+        transfer_tokens(
+            from,
+            to,
+            mint,
+            self.user.to_account_info(),
+            token_program,
+            amount,
+            &[],
+        )
+    }
+
+    // pays `amount` of x (or y) out of the vault, to the user or to the treasury
+    pub fn withdraw_tokens(&self, is_x: bool, amount: u64, to_treasury: bool) -> Result<()> {
+        let (from, mint, token_program) = match is_x {
+            true => (
+                self.vault_x.to_account_info(),
+                &self.mint_x,
+                self.token_program_x.to_account_info(),
+            ),
+            false => (
+                self.vault_y.to_account_info(),
+                &self.mint_y,
+                self.token_program_y.to_account_info(),
+            ),
+        };
+        let to = match (is_x, to_treasury) {
+            (true, false) => self.user_x.to_account_info(),
+            (false, false) => self.user_y.to_account_info(),
+            (true, true) => self.treasury_x.to_account_info(),
+            (false, true) => self.treasury_y.to_account_info(),
+        };
+        let seeds = &[
+            &b"config"[..],
+            &self.config.seed.to_le_bytes(),
+            &[self.config.config_bump],
+        ];
+        let signer_seeds = &[&seeds[..]];
+        transfer_tokens(
+            from,
+            to,
+            mint,
+            self.config.to_account_info(),
+            token_program,
+            amount,
+            signer_seeds,
+        )
+    }
+
+    pub fn mint_lp_tokens(&self, amount: u64) -> Result<()> {
+        let cpi_accounts = MintTo {
+            mint: self.mint_lp.to_account_info(),
+            to: self.user_lp.to_account_info(),
+            authority: self.config.to_account_info(),
+        };
+        let seeds = &[
+            &b"config"[..],
+            &self.config.seed.to_le_bytes(),
+            &[self.config.config_bump],
+        ];
+        let signer_seeds = &[&seeds[..]];
+        let ctx = CpiContext::new_with_signer(
+            self.token_program.to_account_info(),
+            cpi_accounts,
+            signer_seeds,
+        );
+        mint_to(ctx, amount)
+    }
+
+    pub fn burn_lp_tokens(&self, amount: u64) -> Result<()> {
+        let accounts = Burn {
+            mint: self.mint_lp.to_account_info(),
+            from: self.user_lp.to_account_info(),
+            authority: self.user.to_account_info(),
+        };
+        let ctx = CpiContext::new(self.token_program.to_account_info(), accounts);
+        burn(ctx, amount)
+    }
+}
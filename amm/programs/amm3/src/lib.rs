@@ -1,34 +1,287 @@
 #![allow(deprecated, unexpected_cfgs)]
 use anchor_lang::prelude::*;
-mod error;
+pub mod constants;
+pub mod curve;
+pub mod error;
+pub mod events;
 mod instructions;
-mod state;
+pub mod math;
+pub mod state;
+mod utils;
 use instructions::*;
+use state::{LaunchParams, PoolType, Twap};
 declare_id!("E1MtJZn5p34E2SSr41XdFWRRx8erjpWhKtef6Yc3tWQ9");
 
 #[program]
 pub mod amm3 {
     use super::*;
 
+    #[allow(clippy::too_many_arguments)]
     pub fn initialize(
         ctx: Context<Initialize>,
         seed: u64,
+        lp_decimals: u8,
         fee: u16,
+        protocol_fee: u16,
         authority: Option<Pubkey>,
+        pool_type: PoolType,
+        amp: u64,
+        launch: Option<LaunchParams>,
     ) -> Result<()> {
-        ctx.accounts.init(seed, fee, authority, ctx.bumps)?;
+        ctx.accounts.init(
+            seed,
+            lp_decimals,
+            fee,
+            protocol_fee,
+            authority,
+            pool_type,
+            amp,
+            launch,
+            ctx.bumps,
+        )?;
         Ok(())
     }
-    pub fn deposit(ctx: Context<Deposit>, amount: u64, max_x: u64, max_y: u64) -> Result<()> {
-        ctx.accounts.deposit(amount, max_x, max_y)?;
+    pub fn init_fee_tiers(ctx: Context<InitFeeTiers>, authority: Pubkey) -> Result<()> {
+        ctx.accounts.init_fee_tiers(authority, ctx.bumps)?;
         Ok(())
     }
-    pub fn swap(ctx: Context<Swap>, is_x: bool, amount: u64, min: u64) -> Result<()> {
-        ctx.accounts.swap(is_x, amount, min)?;
+    pub fn add_fee_tier(ctx: Context<UpdateFeeTiers>, fee: u16) -> Result<()> {
+        ctx.accounts.add_fee_tier(fee)?;
         Ok(())
     }
-    pub fn withdraw(ctx: Context<Withdraw>, amount: u64, min_x: u64, min_y: u64) -> Result<()> {
-        ctx.accounts.withdraw(amount, min_x, min_y)?;
+    pub fn remove_fee_tier(ctx: Context<UpdateFeeTiers>, fee: u16) -> Result<()> {
+        ctx.accounts.remove_fee_tier(fee)?;
+        Ok(())
+    }
+    pub fn deposit(
+        ctx: Context<Deposit>,
+        amount: u64,
+        max_x: u64,
+        max_y: u64,
+        expires_at: i64,
+        native_sol: bool,
+    ) -> Result<()> {
+        ctx.accounts
+            .deposit(amount, max_x, max_y, expires_at, native_sol)?;
+        Ok(())
+    }
+    pub fn swap(
+        ctx: Context<Swap>,
+        is_x: bool,
+        amount: u64,
+        min: u64,
+        expires_at: i64,
+        native_sol: bool,
+        referral_bps: u16,
+    ) -> Result<()> {
+        ctx.accounts
+            .swap(is_x, amount, min, expires_at, native_sol, referral_bps)?;
+        Ok(())
+    }
+    pub fn swap_exact_out(
+        ctx: Context<Swap>,
+        is_x: bool,
+        amount_out: u64,
+        max_in: u64,
+        expires_at: i64,
+        native_sol: bool,
+        referral_bps: u16,
+    ) -> Result<()> {
+        ctx.accounts.swap_exact_out(
+            is_x,
+            amount_out,
+            max_in,
+            expires_at,
+            native_sol,
+            referral_bps,
+        )?;
+        Ok(())
+    }
+    pub fn route_swap<'info>(
+        ctx: Context<'_, '_, 'info, 'info, RouteSwap<'info>>,
+        amount_in: u64,
+        min_out: u64,
+        expires_at: i64,
+    ) -> Result<()> {
+        ctx.accounts
+            .route_swap(ctx.remaining_accounts, amount_in, min_out, expires_at)?;
+        Ok(())
+    }
+    pub fn withdraw(
+        ctx: Context<Withdraw>,
+        amount: u64,
+        min_x: u64,
+        min_y: u64,
+        expires_at: i64,
+        native_sol: bool,
+    ) -> Result<()> {
+        ctx.accounts
+            .withdraw(amount, min_x, min_y, expires_at, native_sol)?;
+        Ok(())
+    }
+    pub fn deposit_single(
+        ctx: Context<Zap>,
+        is_x: bool,
+        amount_in: u64,
+        min_lp: u64,
+        expires_at: i64,
+    ) -> Result<()> {
+        ctx.accounts
+            .deposit_single(is_x, amount_in, min_lp, expires_at)?;
+        Ok(())
+    }
+    pub fn withdraw_single(
+        ctx: Context<Zap>,
+        is_x: bool,
+        amount: u64,
+        min_out: u64,
+        expires_at: i64,
+    ) -> Result<()> {
+        ctx.accounts
+            .withdraw_single(is_x, amount, min_out, expires_at)?;
+        Ok(())
+    }
+    pub fn flash_loan(ctx: Context<FlashLoan>, is_x: bool, amount: u64) -> Result<()> {
+        ctx.accounts.flash_loan(is_x, amount)?;
+        Ok(())
+    }
+    pub fn flash_repay(ctx: Context<FlashLoan>) -> Result<()> {
+        ctx.accounts.flash_repay()?;
+        Ok(())
+    }
+    pub fn observe(ctx: Context<Observe>, window: u32) -> Result<Twap> {
+        ctx.accounts.observe(window)
+    }
+    pub fn update_fee(ctx: Context<UpdateFee>, fee: u16) -> Result<()> {
+        ctx.accounts.update_fee(fee, ctx.bumps)?;
+        Ok(())
+    }
+    pub fn enable_dynamic_fee(ctx: Context<Update>, max_fee: u16) -> Result<()> {
+        ctx.accounts.enable_dynamic_fee(max_fee)?;
+        Ok(())
+    }
+    pub fn disable_dynamic_fee(ctx: Context<Update>) -> Result<()> {
+        ctx.accounts.disable_dynamic_fee()?;
+        Ok(())
+    }
+    pub fn update_protocol_fee(ctx: Context<Update>, protocol_fee: u16) -> Result<()> {
+        ctx.accounts.update_protocol_fee(protocol_fee)?;
+        Ok(())
+    }
+    pub fn update_referral_fee(ctx: Context<Update>, referral_fee: u16) -> Result<()> {
+        ctx.accounts.update_referral_fee(referral_fee)?;
+        Ok(())
+    }
+    pub fn collect_protocol_fees(ctx: Context<CollectProtocolFees>) -> Result<()> {
+        ctx.accounts.collect_protocol_fees()?;
+        Ok(())
+    }
+    pub fn update_flash_fee(ctx: Context<Update>, flash_fee: u16) -> Result<()> {
+        ctx.accounts.update_flash_fee(flash_fee)?;
+        Ok(())
+    }
+    pub fn ramp_amp(ctx: Context<Update>, target_amp: u64, ramp_end: i64) -> Result<()> {
+        ctx.accounts.ramp_amp(target_amp, ramp_end)?;
+        Ok(())
+    }
+    pub fn stop_ramp_amp(ctx: Context<Update>) -> Result<()> {
+        ctx.accounts.stop_ramp_amp()?;
+        Ok(())
+    }
+    pub fn lock(ctx: Context<Update>) -> Result<()> {
+        ctx.accounts.set_locked(true)?;
+        Ok(())
+    }
+    pub fn unlock(ctx: Context<Update>) -> Result<()> {
+        ctx.accounts.set_locked(false)?;
+        Ok(())
+    }
+    pub fn enable_allowlist(ctx: Context<Update>) -> Result<()> {
+        ctx.accounts.set_allowlist(true)?;
+        Ok(())
+    }
+    pub fn disable_allowlist(ctx: Context<Update>) -> Result<()> {
+        ctx.accounts.set_allowlist(false)?;
+        Ok(())
+    }
+    pub fn add_to_allowlist(ctx: Context<AddAllowlistEntry>, wallet: Pubkey) -> Result<()> {
+        ctx.accounts.add_to_allowlist(wallet, ctx.bumps)?;
+        Ok(())
+    }
+    pub fn remove_from_allowlist(ctx: Context<RemoveAllowlistEntry>) -> Result<()> {
+        ctx.accounts.remove_from_allowlist()?;
+        Ok(())
+    }
+    pub fn start_forced_exit(ctx: Context<Update>) -> Result<()> {
+        ctx.accounts.start_forced_exit()?;
+        Ok(())
+    }
+    pub fn close_pool(ctx: Context<ClosePool>) -> Result<()> {
+        ctx.accounts.close_pool()?;
+        Ok(())
+    }
+    pub fn redeem(ctx: Context<Redeem>, amount: u64) -> Result<()> {
+        ctx.accounts.redeem(amount, ctx.bumps)?;
+        Ok(())
+    }
+    pub fn set_authority(ctx: Context<Update>, new_authority: Pubkey) -> Result<()> {
+        ctx.accounts.set_authority(Some(new_authority))?;
+        Ok(())
+    }
+    pub fn renounce_authority(ctx: Context<Update>) -> Result<()> {
+        ctx.accounts.set_authority(None)?;
+        Ok(())
+    }
+    pub fn create_farm(ctx: Context<CreateFarm>) -> Result<()> {
+        ctx.accounts.create_farm(ctx.bumps)?;
+        Ok(())
+    }
+    pub fn fund_farm(ctx: Context<FundFarm>, amount: u64, end_time: i64) -> Result<()> {
+        ctx.accounts.fund_farm(amount, end_time)?;
+        Ok(())
+    }
+    pub fn stake_lp(ctx: Context<Stake>, amount: u64) -> Result<()> {
+        ctx.accounts.stake_lp(amount, ctx.bumps)?;
+        Ok(())
+    }
+    pub fn unstake_lp(ctx: Context<Stake>, amount: u64) -> Result<()> {
+        ctx.accounts.unstake_lp(amount)?;
+        Ok(())
+    }
+    pub fn claim(ctx: Context<Stake>) -> Result<()> {
+        ctx.accounts.claim()?;
+        Ok(())
+    }
+    pub fn close_stake(ctx: Context<CloseStake>) -> Result<()> {
+        ctx.accounts.close_stake()?;
+        Ok(())
+    }
+    pub fn close_farm(ctx: Context<CloseFarm>) -> Result<()> {
+        ctx.accounts.close_farm()?;
+        Ok(())
+    }
+    pub fn place_order(
+        ctx: Context<PlaceOrder>,
+        id: u64,
+        amount_in: u64,
+        min_out: u64,
+        tip: u64,
+        expires_at: i64,
+    ) -> Result<()> {
+        ctx.accounts
+            .place_order(id, amount_in, min_out, tip, expires_at, ctx.bumps)?;
+        Ok(())
+    }
+    pub fn fill_order(ctx: Context<FillOrder>) -> Result<()> {
+        ctx.accounts.fill_order()?;
+        Ok(())
+    }
+    pub fn cancel_order(ctx: Context<CancelOrder>) -> Result<()> {
+        ctx.accounts.cancel_order()?;
+        Ok(())
+    }
+    pub fn graduate(ctx: Context<Graduate>) -> Result<()> {
+        ctx.accounts.graduate()?;
         Ok(())
     }
 }
@@ -0,0 +1,242 @@
+use anchor_lang::prelude::*;
+
+use crate::constants::MAX_BPS;
+use crate::curve::{mul_div, Rounding, SwapResult};
+use crate::error::AmmError;
+
+pub fn isqrt(n: u128) -> u128 {
+    if n < 2 {
+        return n;
+    }
+    // start above the root and walk down with Newton steps
+    let mut x = 1u128 << (128 - n.leading_zeros()).div_ceil(2);
+    loop {
+        let y = (x + n / x) / 2;
+        if y >= x {
+            return x;
+        }
+        x = y;
+    }
+}
+
+// part of a single-sided deposit to swap first, so that what is left and what comes out of
+// the swap sit in the pool's ratio afterwards. With g = 1 - fee and r the input reserve:
+// s = 2a / (sqrt((1 + g)^2 + 4ga/r) + (1 + g)), worked in Q64.64 and scaled by MAX_BPS
+pub fn zap_split(reserve_in: u64, amount: u64, fee: u16) -> Result<u64> {
+    require!(reserve_in != 0, AmmError::ZeroBalance);
+    require!(fee < MAX_BPS, AmmError::InvalidFee);
+    let bps = MAX_BPS as u128;
+    let g = bps - fee as u128;
+    let b = bps + g;
+    let ratio = ((amount as u128) << 64) / reserve_in as u128;
+    let inner = (4 * bps * g)
+        .checked_mul(ratio)
+        .and_then(|a| a.checked_add((b * b) << 64))
+        .ok_or(AmmError::Overflow)?;
+    let denominator = isqrt(inner) + (b << 32);
+    let split = ((2 * bps * amount as u128) << 32) / denominator;
+    Ok(split as u64)
+}
+
+// StableSwap invariant for two coins: A * n^n * (x + y) + D = A * n^n * D + D^3 / (n^n * x * y).
+// Intermediates are checked u128, so reserves have to stay well below 2^64 for the math to succeed.
+const N_COINS: u128 = 2;
+const STABLE_ITERATIONS: usize = 256;
+
+pub fn stable_compute_d(amp: u64, x: u64, y: u64) -> Result<u128> {
+    let (x, y) = (x as u128, y as u128);
+    let sum = x + y;
+    if sum == 0 {
+        return Ok(0);
+    }
+    require!(x != 0 && y != 0, AmmError::ZeroBalance);
+    let leverage = (amp as u128) * N_COINS;
+    let mut d = sum;
+    for _ in 0..STABLE_ITERATIONS {
+        // d_p = D^3 / (n^n * x * y)
+        let mut d_p = d;
+        d_p = checked_mul_div(d_p, d, x * N_COINS)?;
+        d_p = checked_mul_div(d_p, d, y * N_COINS)?;
+        let previous = d;
+        let numerator = leverage
+            .checked_mul(sum)
+            .and_then(|a| a.checked_add(d_p.checked_mul(N_COINS)?))
+            .ok_or(AmmError::Overflow)?;
+        let denominator = (leverage - 1)
+            .checked_mul(d)
+            .and_then(|a| a.checked_add(d_p.checked_mul(N_COINS + 1)?))
+            .ok_or(AmmError::Overflow)?;
+        d = checked_mul_div(numerator, d, denominator)?;
+        if d.abs_diff(previous) <= 1 {
+            return Ok(d);
+        }
+    }
+    err!(AmmError::CurveError)
+}
+
+// the other reserve once one side is moved to `x`, keeping D constant
+pub fn stable_compute_y(amp: u64, x: u128, d: u128) -> Result<u128> {
+    require!(x != 0, AmmError::ZeroBalance);
+    let leverage = (amp as u128) * N_COINS;
+    // c = D^3 / (n^n * x * leverage), b = x + D / leverage
+    let c = checked_mul_div(d, d, x * N_COINS)?;
+    let c = checked_mul_div(c, d, leverage * N_COINS)?;
+    let b = x + d / leverage;
+    let mut y = d;
+    for _ in 0..STABLE_ITERATIONS {
+        let previous = y;
+        let numerator = y
+            .checked_mul(y)
+            .and_then(|a| a.checked_add(c))
+            .ok_or(AmmError::Overflow)?;
+        let denominator = (2 * y + b).checked_sub(d).ok_or(AmmError::Underflow)?;
+        y = numerator / denominator;
+        if y.abs_diff(previous) <= 1 {
+            return Ok(y);
+        }
+    }
+    err!(AmmError::CurveError)
+}
+
+// exact-input stable swap, the fee is taken from the input like on the constant-product curve
+pub fn stable_swap_exact_in(
+    amp: u64,
+    reserve_in: u64,
+    reserve_out: u64,
+    amount: u64,
+    fee: u16,
+) -> Result<SwapResult> {
+    require!(fee < MAX_BPS, AmmError::InvalidFee);
+    let fee_amount = mul_div(amount, fee as u64, MAX_BPS as u64, Rounding::Up)?;
+    let net_in = amount - fee_amount;
+    let d = stable_compute_d(amp, reserve_in, reserve_out)?;
+    let new_in = (reserve_in as u128) + (net_in as u128);
+    let new_out = stable_compute_y(amp, new_in, d)?;
+    // one unit is kept back so rounding in the invariant always favours the pool
+    let withdraw = (reserve_out as u128)
+        .checked_sub(new_out)
+        .and_then(|a| a.checked_sub(1))
+        .unwrap_or(0) as u64;
+    require!(withdraw != 0, AmmError::InvalidAmount);
+    Ok(SwapResult {
+        deposit: amount,
+        withdraw,
+        fee: fee_amount,
+    })
+}
+
+// input (fee included) a stable pool needs to give out exactly `amount_out`; returns (amount_in, fee)
+pub fn stable_exact_out_input(
+    amp: u64,
+    reserve_in: u64,
+    reserve_out: u64,
+    amount_out: u64,
+    fee: u16,
+) -> Result<(u64, u64)> {
+    require!(amount_out < reserve_out, AmmError::Insufficientbalance);
+    require!(fee < MAX_BPS, AmmError::InvalidFee);
+    let d = stable_compute_d(amp, reserve_in, reserve_out)?;
+    let new_in = stable_compute_y(amp, (reserve_out - amount_out) as u128, d)?;
+    let net_in = new_in
+        .checked_sub(reserve_in as u128)
+        .and_then(|a| a.checked_add(1))
+        .ok_or(AmmError::Underflow)?;
+    let net_in = u64::try_from(net_in).map_err(|_| error!(AmmError::Overflow))?;
+    let amount_in = mul_div(net_in, MAX_BPS as u64, (MAX_BPS - fee) as u64, Rounding::Up)?;
+    Ok((amount_in, amount_in - net_in))
+}
+
+// stable counterpart of `zap_split`: there is no closed form, so bisect on the swapped part
+// until it is within ~1e-6 of `amount`, rounding towards swapping less
+pub fn stable_zap_split(
+    amp: u64,
+    reserve_in: u64,
+    reserve_out: u64,
+    amount: u64,
+    fee: u16,
+) -> Result<u64> {
+    require!(fee < MAX_BPS, AmmError::InvalidFee);
+    let d = stable_compute_d(amp, reserve_in, reserve_out)?;
+    let tolerance = (amount >> 20).max(1);
+    let (mut lo, mut hi) = (0u64, amount);
+    while hi - lo > tolerance {
+        let mid = lo + (hi - lo) / 2;
+        let net_in = mid - mul_div(mid, fee as u64, MAX_BPS as u64, Rounding::Up)?;
+        let new_out = stable_compute_y(amp, (reserve_in as u128) + (net_in as u128), d)?;
+        let out = (reserve_out as u128).saturating_sub(new_out + 1);
+        // swapping `mid` is not too much while the rest still covers the share of what came out
+        let rest = ((amount - mid) as u128) * ((reserve_out as u128) - out);
+        let share = out
+            .checked_mul((reserve_in as u128) + (mid as u128))
+            .ok_or(AmmError::Overflow)?;
+        match rest >= share {
+            true => lo = mid,
+            false => hi = mid,
+        }
+    }
+    Ok(lo)
+}
+
+// marginal price of x in y on the stable curve, Q64.64. Differentiating the invariant with
+// L = A * n and D_p = D^3 / (n^n * x * y) gives -dy/dx = y * (L * x + D_p) / (x * (L * y + D_p))
+pub fn stable_spot_price(amp: u64, x: u64, y: u64) -> Result<u128> {
+    let d = stable_compute_d(amp, x, y)?;
+    let (x, y) = (x as u128, y as u128);
+    let leverage = (amp as u128) * N_COINS;
+    let d_p = checked_mul_div(d, d, x * N_COINS)?;
+    let d_p = checked_mul_div(d_p, d, y * N_COINS)?;
+    let a = leverage
+        .checked_mul(x)
+        .and_then(|a| a.checked_add(d_p))
+        .ok_or(AmmError::Overflow)?;
+    let b = leverage
+        .checked_mul(y)
+        .and_then(|b| b.checked_add(d_p))
+        .ok_or(AmmError::Overflow)?;
+    // only the ratio of the two terms matters, keep them below 2^64 so the products fit
+    let shift = (128 - a.max(b).leading_zeros()).saturating_sub(64);
+    Ok(q64_ratio(y * (a >> shift), x * (b >> shift)))
+}
+
+// num / den in Q64.64, both shifted down together when num leaves no room for the fraction
+pub fn q64_ratio(num: u128, den: u128) -> u128 {
+    let shift = (128 - num.leading_zeros()).saturating_sub(64);
+    match den >> shift {
+        0 => u128::MAX,
+        den => ((num >> shift) << 64) / den,
+    }
+}
+
+fn checked_mul_div(a: u128, b: u128, c: u128) -> Result<u128> {
+    require!(c != 0, AmmError::ZeroBalance);
+    Ok(a.checked_mul(b).ok_or(AmmError::Overflow)? / c)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // the spot price matches what a small fee-free swap pays per unit in
+    #[test]
+    fn stable_spot_price_is_the_curve_slope() {
+        let (x, y) = (1_000_000_000, 3_000_000_000);
+        let dx = 10_000;
+        let price = stable_spot_price(100, x, y).unwrap();
+        let out = stable_swap_exact_in(100, x, y, dx, 0).unwrap().withdraw as u128 + 1;
+        let quoted = (price * dx as u128) >> 64;
+        assert!(quoted.abs_diff(out) * 10_000 <= out, "{quoted} vs {out}");
+
+        // balanced pools trade at par and the price is symmetric
+        let par = stable_spot_price(100, x, x).unwrap();
+        assert!(par.abs_diff(1 << 64) < 1 << 20);
+        let inverse = stable_spot_price(100, y, x).unwrap();
+        assert!(((price >> 32) * (inverse >> 32)).abs_diff(1 << 64) < 1 << 50);
+    }
+
+    #[test]
+    fn q64_ratio_keeps_precision_on_large_operands() {
+        assert_eq!(q64_ratio(3, 2), 3 << 63);
+        assert_eq!(q64_ratio(3 << 100, 2 << 100), 3 << 63);
+        assert_eq!(q64_ratio(1, 0), u128::MAX);
+    }
+}
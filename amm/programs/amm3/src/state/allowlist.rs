@@ -0,0 +1,10 @@
+use anchor_lang::prelude::*;
+
+// a wallet approved to deposit, swap and withdraw on an allowlisted pool
+#[account]
+#[derive(InitSpace)]
+pub struct AllowlistEntry {
+    pub config: Pubkey,
+    pub wallet: Pubkey,
+    pub bump: u8,
+}
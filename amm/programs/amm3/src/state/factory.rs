@@ -0,0 +1,63 @@
+use crate::constants::MAX_BPS;
+use crate::error::AmmError;
+use anchor_lang::prelude::*;
+
+pub const POOLS_PER_PAGE: usize = 64;
+pub const MAX_FEE_TIERS: usize = 16;
+
+// program-wide state, created with the first pool
+#[account]
+#[derive(InitSpace)]
+pub struct Factory {
+    pub pool_count: u64,
+    pub bump: u8,
+}
+
+impl Factory {
+    // index page the next pool is listed on
+    pub fn next_page(&self) -> u64 {
+        self.pool_count / POOLS_PER_PAGE as u64
+    }
+}
+
+// the fees, in bps, that new pools may be created at; managed by governance
+#[account]
+#[derive(InitSpace)]
+pub struct FeeTiers {
+    pub authority: Pubkey,
+    pub bump: u8,
+    #[max_len(MAX_FEE_TIERS)]
+    pub fees: Vec<u16>,
+}
+
+impl FeeTiers {
+    pub fn check_fee(&self, fee: u16) -> Result<()> {
+        require!(
+            fee < MAX_BPS && self.fees.contains(&fee),
+            AmmError::InvalidFee
+        );
+        Ok(())
+    }
+}
+
+// the one pool for a pair at a fee tier, seeded by its mints in canonical (ascending) order
+// and the tier
+#[account]
+#[derive(InitSpace)]
+pub struct Pair {
+    pub config: Pubkey,
+    pub mint_x: Pubkey,
+    pub mint_y: Pubkey,
+    pub fee: u16,
+    pub bump: u8,
+}
+
+// fixed-size page of pool configs, in creation order; closed pools are dropped from it
+#[account]
+#[derive(InitSpace)]
+pub struct PoolIndex {
+    pub page: u64,
+    pub bump: u8,
+    #[max_len(POOLS_PER_PAGE)]
+    pub pools: Vec<Pubkey>,
+}
@@ -0,0 +1,191 @@
+use anchor_lang::prelude::*;
+
+use crate::constants::REWARD_PRECISION;
+use crate::error::AmmError;
+
+// LP staking rewards for one pool, emitted at `emission_rate` per second until `end_time`
+#[account]
+#[derive(InitSpace)]
+pub struct Farm {
+    pub config: Pubkey,
+    pub reward_mint: Pubkey,
+    pub emission_rate: u64,
+    pub end_time: i64,
+    pub last_update: i64,
+    pub reward_per_share: u128, // rewards per staked LP token, scaled by REWARD_PRECISION
+    pub total_staked: u64,
+    pub stakers: u64, // open UserStake accounts, all of which are settled before the farm closes
+    pub bump: u8,
+}
+
+impl Farm {
+    // brings the accumulator up to `now`, nothing is emitted past `end_time`. What would have
+    // been emitted while nothing is staked is rolled forward: spread over the rest of the
+    // schedule, or left owed past `end_time` for the next stakers or the next funding
+    pub fn accrue(&mut self, now: i64) -> Result<()> {
+        let until = now.min(self.end_time);
+        if until <= self.last_update {
+            return Ok(());
+        }
+        if self.total_staked == 0 {
+            if until < self.end_time {
+                let owed =
+                    (self.emission_rate as u128) * ((self.end_time - self.last_update) as u128);
+                let rate = owed / ((self.end_time - until) as u128);
+                self.emission_rate = u64::try_from(rate).map_err(|_| error!(AmmError::Overflow))?;
+                self.last_update = until;
+            }
+            return Ok(());
+        }
+        let emitted = (self.emission_rate as u128) * ((until - self.last_update) as u128);
+        let per_share = emitted
+            .checked_mul(REWARD_PRECISION)
+            .ok_or(AmmError::Overflow)?
+            / self.total_staked as u128;
+        self.reward_per_share = self
+            .reward_per_share
+            .checked_add(per_share)
+            .ok_or(AmmError::Overflow)?;
+        self.last_update = until;
+        Ok(())
+    }
+
+    // adds `amount` to what is left to emit and spreads it all evenly until `end_time`,
+    // which covers both topping up and extending a farm
+    pub fn fund(&mut self, now: i64, amount: u64, end_time: i64) -> Result<()> {
+        require!(
+            end_time > now && end_time >= self.end_time,
+            AmmError::InvalidFarmSchedule
+        );
+        self.accrue(now)?;
+        let leftover = match self.last_update < self.end_time {
+            true => (self.emission_rate as u128) * ((self.end_time - self.last_update) as u128),
+            false => 0,
+        };
+        let rate = (leftover + amount as u128) / ((end_time - now) as u128);
+        self.emission_rate = u64::try_from(rate).map_err(|_| error!(AmmError::Overflow))?;
+        self.end_time = end_time;
+        self.last_update = now;
+        Ok(())
+    }
+}
+
+#[account]
+#[derive(InitSpace)]
+pub struct UserStake {
+    pub farm: Pubkey,
+    pub owner: Pubkey,
+    pub amount: u64,
+    pub reward_per_share_paid: u128, // reward_per_share at the last settlement
+    pub pending: u64,
+    pub bump: u8,
+}
+
+impl UserStake {
+    // moves what was earned since the last settlement into `pending`. It cannot fail, so
+    // reward accounting never keeps a staker from their LP
+    pub fn settle(&mut self, reward_per_share: u128) {
+        let delta = reward_per_share.saturating_sub(self.reward_per_share_paid);
+        let amount = self.amount as u128;
+        let earned = (delta / REWARD_PRECISION)
+            .saturating_mul(amount)
+            .saturating_add(delta % REWARD_PRECISION * amount / REWARD_PRECISION);
+        let earned = u64::try_from(earned).unwrap_or(u64::MAX);
+        self.pending = self.pending.saturating_add(earned);
+        self.reward_per_share_paid = reward_per_share;
+    }
+
+    // changes the stake after a settlement, so earlier emissions are not paid on it
+    pub fn set_amount(&mut self, amount: u64, reward_per_share: u128) {
+        self.amount = amount;
+        self.reward_per_share_paid = reward_per_share;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn farm(emission_rate: u64, end_time: i64) -> Farm {
+        Farm {
+            config: Pubkey::default(),
+            reward_mint: Pubkey::default(),
+            emission_rate,
+            end_time,
+            last_update: 0,
+            reward_per_share: 0,
+            total_staked: 0,
+            stakers: 0,
+            bump: 0,
+        }
+    }
+
+    fn stake(amount: u64, reward_per_share: u128) -> UserStake {
+        let mut stake = UserStake {
+            farm: Pubkey::default(),
+            owner: Pubkey::default(),
+            amount: 0,
+            reward_per_share_paid: 0,
+            pending: 0,
+            bump: 0,
+        };
+        stake.set_amount(amount, reward_per_share);
+        stake
+    }
+
+    #[test]
+    fn splits_emissions_by_stake() {
+        let mut farm = farm(1_000, 100);
+        farm.total_staked = 4;
+        let mut a = stake(1, 0);
+        let mut b = stake(3, 0);
+        farm.accrue(10).unwrap();
+        a.settle(farm.reward_per_share);
+        b.settle(farm.reward_per_share);
+        assert_eq!((a.pending, b.pending), (2_500, 7_500));
+
+        // nothing is emitted past the end
+        farm.accrue(1_000).unwrap();
+        a.settle(farm.reward_per_share);
+        assert_eq!(a.pending, 25_000);
+    }
+
+    #[test]
+    fn rolls_idle_emissions_forward() {
+        let mut farm = farm(1_000, 100);
+        farm.accrue(50).unwrap();
+        assert_eq!((farm.emission_rate, farm.last_update), (2_000, 50));
+
+        farm.total_staked = 1;
+        let mut a = stake(1, farm.reward_per_share);
+        farm.accrue(100).unwrap();
+        a.settle(farm.reward_per_share);
+        assert_eq!(a.pending, 100_000);
+    }
+
+    #[test]
+    fn keeps_idle_emissions_owed_past_the_end() {
+        let mut farm = farm(1_000, 100);
+        farm.accrue(200).unwrap();
+        assert_eq!(farm.last_update, 0);
+
+        // a new funding picks up what was never emitted
+        farm.fund(200, 50_000, 300).unwrap();
+        assert_eq!(farm.emission_rate, 1_500);
+    }
+
+    // a dust stake pushes the accumulator as high as it goes, a large stake joining after must
+    // still settle and be able to leave
+    #[test]
+    fn large_stakes_settle_after_a_dust_staker() {
+        let mut farm = farm(u64::MAX / 1_000, 1_000);
+        farm.total_staked = 1;
+        farm.accrue(1_000).unwrap();
+
+        let mut whale = stake(u64::MAX, 0);
+        whale.settle(farm.reward_per_share);
+        assert_eq!(whale.pending, u64::MAX);
+        whale.set_amount(0, farm.reward_per_share);
+        assert_eq!(whale.amount, 0);
+    }
+}
@@ -1,2 +1,13 @@
+pub mod allowlist;
+pub mod factory;
+pub mod farm;
+pub mod oracle;
+pub mod order;
+#[allow(clippy::module_inception)]
 pub mod state;
+pub use allowlist::*;
+pub use factory::*;
+pub use farm::*;
+pub use oracle::*;
+pub use order::*;
 pub use state::*;
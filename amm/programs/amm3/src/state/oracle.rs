@@ -0,0 +1,212 @@
+use anchor_lang::prelude::*;
+
+use crate::error::AmmError;
+use crate::state::Config;
+
+pub const OBSERVATION_CAPACITY: usize = 64;
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, InitSpace)]
+pub struct Observation {
+    pub timestamp: i64,
+    // running sums of the Q64.64 spot price times seconds, allowed to wrap
+    pub price_x_cumulative: u128,
+    pub price_y_cumulative: u128,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy)]
+pub struct Twap {
+    pub price_x: u128, // Q64.64 amount of y per x
+    pub price_y: u128, // Q64.64 amount of x per y
+}
+
+#[account]
+#[derive(InitSpace)]
+pub struct Oracle {
+    pub config: Pubkey,
+    pub index: u16, // position of the latest observation
+    pub bump: u8,
+    // the pool's reserves after the latest update, which price the time since then
+    pub reserve_x: u64,
+    pub reserve_y: u64,
+    #[max_len(OBSERVATION_CAPACITY)]
+    pub observations: Vec<Observation>,
+}
+
+impl Oracle {
+    pub fn latest(&self) -> Observation {
+        self.observations[self.index as usize]
+    }
+
+    // called after a trade with the reserves it left: the time since the latest observation is
+    // priced at the reserves recorded then, so tokens sent straight to a vault never count
+    pub fn update(&mut self, config: &Config, now: i64, reserve_x: u64, reserve_y: u64) {
+        let last = self.latest();
+        if now > last.timestamp {
+            let prices = config.spot_prices(self.reserve_x, self.reserve_y, now);
+            let observation = Self::extrapolate(&last, now, prices);
+            if self.observations.len() < OBSERVATION_CAPACITY {
+                self.observations.push(observation);
+                self.index = (self.observations.len() - 1) as u16;
+            } else {
+                self.index = ((self.index as usize + 1) % OBSERVATION_CAPACITY) as u16;
+                self.observations[self.index as usize] = observation;
+            }
+        }
+        self.reserve_x = reserve_x;
+        self.reserve_y = reserve_y;
+    }
+
+    // `prices` are the Q64.64 spot prices that held since `last`
+    pub fn extrapolate(last: &Observation, at: i64, prices: (u128, u128)) -> Observation {
+        let elapsed = at.saturating_sub(last.timestamp) as u128;
+        let (price_x, price_y) = prices;
+        Observation {
+            timestamp: at,
+            price_x_cumulative: last
+                .price_x_cumulative
+                .wrapping_add(price_x.wrapping_mul(elapsed)),
+            price_y_cumulative: last
+                .price_y_cumulative
+                .wrapping_add(price_y.wrapping_mul(elapsed)),
+        }
+    }
+
+    // cumulative prices at `target`, interpolated between the two observations around it
+    pub fn cumulative_at(&self, config: &Config, target: i64) -> Result<Observation> {
+        let last = self.latest();
+        if target >= last.timestamp {
+            let prices = config.spot_prices(self.reserve_x, self.reserve_y, target);
+            return Ok(Self::extrapolate(&last, target, prices));
+        }
+        let len = self.observations.len();
+        // walk backwards from the latest observation to the oldest one still in the buffer
+        let mut after = last;
+        for i in 1..len {
+            let before = self.observations[(self.index as usize + len - i) % len];
+            if before.timestamp <= target {
+                let span = (after.timestamp - before.timestamp) as u128;
+                let offset = (target - before.timestamp) as u128;
+                return Ok(Observation {
+                    timestamp: target,
+                    price_x_cumulative: Self::interpolate(
+                        before.price_x_cumulative,
+                        after.price_x_cumulative,
+                        offset,
+                        span,
+                    ),
+                    price_y_cumulative: Self::interpolate(
+                        before.price_y_cumulative,
+                        after.price_y_cumulative,
+                        offset,
+                        span,
+                    ),
+                });
+            }
+            after = before;
+        }
+        err!(AmmError::ObservationTooOld)
+    }
+
+    fn interpolate(from: u128, to: u128, offset: u128, span: u128) -> u128 {
+        // split the division so `delta * offset` cannot overflow
+        let delta = to.wrapping_sub(from);
+        from.wrapping_add(delta / span * offset + delta % span * offset / span)
+    }
+
+    pub fn observe(&self, config: &Config, now: i64, window: u32) -> Result<Twap> {
+        require!(window > 0, AmmError::InvalidAmount);
+        let end = self.cumulative_at(config, now)?;
+        let start = self.cumulative_at(config, now - window as i64)?;
+        Ok(Twap {
+            price_x: end.price_x_cumulative.wrapping_sub(start.price_x_cumulative) / window as u128,
+            price_y: end.price_y_cumulative.wrapping_sub(start.price_y_cumulative) / window as u128,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::math::stable_spot_price;
+    use crate::state::{DynamicFee, LaunchParams, PoolType};
+
+    fn config(pool_type: PoolType) -> Config {
+        Config {
+            seed: 1,
+            authority: None,
+            initializer: Pubkey::default(),
+            mint_x: Pubkey::default(),
+            mint_y: Pubkey::default(),
+            decimals_x: 6,
+            decimals_y: 6,
+            lp_decimals: 6,
+            fee: 30,
+            protocol_fee: 0,
+            referral_fee: 0,
+            locked: false,
+            allowlist: false,
+            flash_fee: 0,
+            flash_loan_amount: 0,
+            flash_loan_is_x: false,
+            pool_type,
+            amp_initial: 100,
+            amp_target: 100,
+            amp_ramp_start: 0,
+            amp_ramp_end: 0,
+            launch: LaunchParams::default(),
+            dynamic_fee: DynamicFee::default(),
+            close_after: 0,
+            open_orders: 0,
+            config_bump: 0,
+            lp_bump: 0,
+            locked_lp_bump: 0,
+            treasury_x_bump: 0,
+            treasury_y_bump: 0,
+            oracle_bump: 0,
+        }
+    }
+
+    fn oracle() -> Oracle {
+        Oracle {
+            config: Pubkey::default(),
+            index: 0,
+            bump: 0,
+            reserve_x: 0,
+            reserve_y: 0,
+            observations: vec![Observation {
+                timestamp: 0,
+                price_x_cumulative: 0,
+                price_y_cumulative: 0,
+            }],
+        }
+    }
+
+    // the time after an update is priced at the reserves the trade left, whatever the vaults
+    // hold by the time the price is read
+    #[test]
+    fn prices_time_at_the_recorded_reserves() {
+        let config = config(PoolType::ConstantProduct);
+        let mut oracle = oracle();
+        oracle.update(&config, 0, 1_000, 2_000);
+        let twap = oracle.observe(&config, 100, 100).unwrap();
+        assert_eq!((twap.price_x, twap.price_y), (2 << 64, 1 << 63));
+
+        // the next trade closes the segment at the old reserves before recording its own
+        oracle.update(&config, 100, 2_000, 1_000);
+        assert_eq!(oracle.observations.len(), 2);
+        let twap = oracle.observe(&config, 200, 200).unwrap();
+        assert_eq!(twap.price_x, ((2 << 64) + (1 << 63)) / 2);
+    }
+
+    // a stable pool a third off balance still trades close to par, not at the reserve ratio
+    #[test]
+    fn prices_stable_pools_on_the_curve() {
+        let config = config(PoolType::StableSwap);
+        let mut oracle = oracle();
+        oracle.update(&config, 0, 1_000_000_000, 3_000_000_000);
+        let twap = oracle.observe(&config, 100, 100).unwrap();
+        let price_x = stable_spot_price(100, 1_000_000_000, 3_000_000_000).unwrap();
+        assert_eq!(twap.price_x, price_x);
+        assert!(price_x > 1 << 64 && price_x < 2 << 64);
+    }
+}
@@ -0,0 +1,19 @@
+use anchor_lang::prelude::*;
+
+// a resting limit order: `amount_in` of `mint_in` sits in the order's escrow until a keeper
+// can swap it through the pool for at least `min_out` of `mint_out`
+#[account]
+#[derive(InitSpace)]
+pub struct Order {
+    pub config: Pubkey,
+    pub owner: Pubkey,
+    pub id: u64,
+    pub mint_in: Pubkey,
+    pub mint_out: Pubkey,
+    pub is_x: bool, // true when x is sold for y
+    pub amount_in: u64,
+    pub min_out: u64, // the limit, as what reaches the owner for the whole order
+    pub tip: u64,     // lamports held by the order for whoever fills it
+    pub expires_at: i64,
+    pub bump: u8,
+}
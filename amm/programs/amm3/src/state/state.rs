@@ -1,13 +1,335 @@
 use anchor_lang::prelude::*;
+
+use crate::constants::{MAX_BPS, VOLATILITY_FEE_RATE, VOLATILITY_HALF_LIFE};
+use crate::curve::{exact_out_input, swap_exact_in, SwapResult};
+use crate::error::AmmError;
+use crate::math::{
+    q64_ratio, stable_exact_out_input, stable_spot_price, stable_swap_exact_in, stable_zap_split,
+    zap_split,
+};
+use crate::state::AllowlistEntry;
+use crate::utils::is_native_mint;
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, InitSpace)]
+pub enum PoolType {
+    ConstantProduct,
+    StableSwap,
+    Launch,
+}
+
+// bonding-curve launch: swaps price against the real vaults plus these virtual reserves
+// until both vaults reach their target, then `graduate` turns the pool constant-product
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Default, InitSpace)]
+pub struct LaunchParams {
+    pub virtual_x: u64,
+    pub virtual_y: u64,
+    pub target_x: u64,
+    pub target_y: u64,
+}
+
+// volatility-driven fee: `fee` floats between the pool's tier fee and `max_fee`, lifted by
+// a decaying sum of the price moves (bps) swaps have made
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Default, InitSpace)]
+pub struct DynamicFee {
+    pub enabled: bool,
+    pub max_fee: u16,
+    pub volatility: u64,
+    pub last_slot: u64,
+    pub fee: u16, // the effective fee as of the last refresh
+}
+
 #[account]
 #[derive(InitSpace)]
 pub struct Config {
     pub seed: u64,
     pub authority: Option<Pubkey>,
+    pub initializer: Pubkey, // paid the pool's rent, gets it back when the pool is closed
     pub mint_x: Pubkey,
     pub mint_y: Pubkey,
-    pub fee: u16,
+    pub decimals_x: u8,
+    pub decimals_y: u8,
+    pub lp_decimals: u8, // chosen at initialize, scales the LP minted on the first deposit
+    pub fee: u16, // the pool's fee tier, and the floor of a dynamic fee
+    pub protocol_fee: u16, // share of the swap fee sent to the treasury, in bps
+    pub referral_fee: u16, // share of the swap fee paid to a referrer, in bps
     pub locked: bool,
+    pub allowlist: bool, // only wallets with an allowlist entry may deposit, swap or withdraw
+    pub flash_fee: u16, // charged on flash loan principal, in bps
+    // outstanding flash loan, zero when none is open
+    pub flash_loan_amount: u64,
+    pub flash_loan_is_x: bool,
+    pub pool_type: PoolType,
+    // StableSwap amplification, ramped linearly from amp_initial to amp_target
+    pub amp_initial: u64,
+    pub amp_target: u64,
+    pub amp_ramp_start: i64,
+    pub amp_ramp_end: i64,
+    pub launch: LaunchParams, // zeroed outside launch mode
+    pub dynamic_fee: DynamicFee,
+    // once set, deposits stop so LPs can wind the pool down; zero otherwise
+    pub close_after: i64,
+    pub open_orders: u64, // limit orders escrowed against the pool, which needs the config to settle them
     pub config_bump: u8,
     pub lp_bump: u8,
+    pub locked_lp_bump: u8,
+    pub treasury_x_bump: u8,
+    pub treasury_y_bump: u8,
+    pub oracle_bump: u8,
+}
+
+impl Config {
+    pub fn check_authority(&self, signer: &Pubkey) -> Result<()> {
+        let authority = self.authority.ok_or(AmmError::NoAuthoritySet)?;
+        require_keys_eq!(authority, *signer, AmmError::InvalidAuthority);
+        Ok(())
+    }
+
+    // deposits, swaps and withdrawals are refused while locked or while a flash loan is open
+    pub fn check_unlocked(&self) -> Result<()> {
+        require!(!self.locked, AmmError::PoolLocked);
+        require!(self.flash_loan_amount == 0, AmmError::FlashLoanActive);
+        Ok(())
+    }
+
+    pub fn check_not_closing(&self) -> Result<()> {
+        require!(self.close_after == 0, AmmError::PoolClosing);
+        Ok(())
+    }
+
+    // whether a forced exit's notice has run out
+    pub fn forced_exit_due(&self, now: i64) -> bool {
+        self.close_after != 0 && now >= self.close_after
+    }
+
+    // on allowlisted pools `entry` must be the user's entry for this pool
+    pub fn check_allowlisted(
+        &self,
+        config: &Pubkey,
+        user: &Pubkey,
+        entry: Option<&Account<AllowlistEntry>>,
+    ) -> Result<()> {
+        if !self.allowlist {
+            return Ok(());
+        }
+        let entry = entry.ok_or(AmmError::NotAllowlisted)?;
+        require!(
+            entry.config == *config && entry.wallet == *user,
+            AmmError::NotAllowlisted
+        );
+        Ok(())
+    }
+
+    // native SOL mode only makes sense when one side of the pool is wrapped SOL
+    pub fn check_native(&self) -> Result<()> {
+        require!(
+            is_native_mint(&self.mint_x) || is_native_mint(&self.mint_y),
+            AmmError::InvalidToken
+        );
+        Ok(())
+    }
+
+    // part of a swap fee that goes to the treasury, the rest stays in the vault for LPs
+    pub fn protocol_fee_amount(&self, fee: u64) -> Result<u64> {
+        let amount = (fee as u128)
+            .checked_mul(self.protocol_fee as u128)
+            .ok_or(AmmError::Overflow)?
+            / MAX_BPS as u128;
+        Ok(amount as u64)
+    }
+
+    // part of a swap fee owed to the referrer at the `referral_bps` the front-end asks for,
+    // capped by the pool's `referral_fee` and taken out of the LP share
+    pub fn referral_fee_amount(&self, fee: u64, referral_bps: u16) -> Result<u64> {
+        require!(
+            referral_bps <= self.referral_fee,
+            AmmError::ReferralFeeTooHigh
+        );
+        let amount = (fee as u128)
+            .checked_mul(referral_bps as u128)
+            .ok_or(AmmError::Overflow)?
+            / MAX_BPS as u128;
+        Ok(amount as u64)
+    }
+
+    // the fee swaps are charged, in bps
+    pub fn swap_fee(&self) -> u16 {
+        match self.dynamic_fee.enabled {
+            true => self.dynamic_fee.fee,
+            false => self.fee,
+        }
+    }
+
+    // decays the volatility accumulator to `slot` and moves the dynamic fee to match; swaps
+    // call this before quoting
+    pub fn refresh_dynamic_fee(&mut self, slot: u64) {
+        if !self.dynamic_fee.enabled {
+            return;
+        }
+        let dynamic = &mut self.dynamic_fee;
+        let elapsed = slot.saturating_sub(dynamic.last_slot);
+        let mut volatility = match elapsed / VOLATILITY_HALF_LIFE {
+            halvings if halvings < 64 => dynamic.volatility >> halvings,
+            _ => 0,
+        };
+        // linear between halvings
+        volatility -= (volatility as u128 * (elapsed % VOLATILITY_HALF_LIFE) as u128
+            / (2 * VOLATILITY_HALF_LIFE) as u128) as u64;
+        dynamic.volatility = volatility;
+        dynamic.last_slot = slot;
+        self.apply_dynamic_fee();
+    }
+
+    // marginal prices at these reserves in Q64.64, y per x and x per y: the reserve ratio on a
+    // constant-product curve, the invariant's slope on a stable one
+    pub fn spot_prices(&self, reserve_x: u64, reserve_y: u64, now: i64) -> (u128, u128) {
+        if reserve_x == 0 || reserve_y == 0 {
+            return (0, 0);
+        }
+        let (x, y) = (reserve_x as u128, reserve_y as u128);
+        let ratio = (q64_ratio(y, x), q64_ratio(x, y));
+        if self.pool_type != PoolType::StableSwap {
+            return ratio;
+        }
+        let amp = self.amp(now);
+        match (
+            stable_spot_price(amp, reserve_x, reserve_y),
+            stable_spot_price(amp, reserve_y, reserve_x),
+        ) {
+            (Ok(price_x), Ok(price_y)) => (price_x, price_y),
+            _ => ratio,
+        }
+    }
+
+    // adds the relative price move between the reserves before and after a swap
+    pub fn record_price_move(&mut self, before: (u64, u64), after: (u64, u64), now: i64) {
+        if !self.dynamic_fee.enabled {
+            return;
+        }
+        let (old_price, _) = self.spot_prices(before.0, before.1, now);
+        let (new_price, _) = self.spot_prices(after.0, after.1, now);
+        if old_price == 0 {
+            return;
+        }
+        let move_bps = new_price.abs_diff(old_price).saturating_mul(MAX_BPS as u128) / old_price;
+        let dynamic = &mut self.dynamic_fee;
+        dynamic.volatility = dynamic
+            .volatility
+            .saturating_add(move_bps.min(u64::MAX as u128) as u64);
+        self.apply_dynamic_fee();
+    }
+
+    fn apply_dynamic_fee(&mut self) {
+        let dynamic = &mut self.dynamic_fee;
+        let bonus = dynamic.volatility as u128 * VOLATILITY_FEE_RATE as u128 / MAX_BPS as u128;
+        let fee = (self.fee as u128 + bonus).min(dynamic.max_fee as u128);
+        dynamic.fee = fee as u16;
+    }
+
+    pub fn amp(&self, now: i64) -> u64 {
+        if now >= self.amp_ramp_end || self.amp_ramp_end <= self.amp_ramp_start {
+            return self.amp_target;
+        }
+        let elapsed = now.saturating_sub(self.amp_ramp_start).max(0) as u128;
+        let duration = (self.amp_ramp_end - self.amp_ramp_start) as u128;
+        let (from, to) = (self.amp_initial as u128, self.amp_target as u128);
+        let amp = match to >= from {
+            true => from + (to - from) * elapsed / duration,
+            false => from - (from - to) * elapsed / duration,
+        };
+        amp as u64
+    }
+
+    // exact-input swap on this pool's curve, `is_x` meaning x is paid in
+    pub fn quote_exact_in(
+        &self,
+        reserve_x: u64,
+        reserve_y: u64,
+        is_x: bool,
+        amount: u64,
+        now: i64,
+    ) -> Result<SwapResult> {
+        let fee = self.swap_fee();
+        let (reserve_in, reserve_out) = match is_x {
+            true => (reserve_x, reserve_y),
+            false => (reserve_y, reserve_x),
+        };
+        match self.pool_type {
+            PoolType::ConstantProduct => swap_exact_in(reserve_in, reserve_out, amount, fee),
+            PoolType::StableSwap => {
+                stable_swap_exact_in(self.amp(now), reserve_in, reserve_out, amount, fee)
+            }
+            PoolType::Launch => {
+                let (curve_in, curve_out) = self.launch_reserves(is_x, reserve_in, reserve_out)?;
+                let res = swap_exact_in(curve_in, curve_out, amount, fee)?;
+                // only real tokens can leave the vault
+                require!(res.withdraw < reserve_out, AmmError::Insufficientbalance);
+                Ok(res)
+            }
+        }
+    }
+
+    // input (fee included) needed to take exactly `amount_out`; returns (amount_in, fee)
+    pub fn quote_exact_out(
+        &self,
+        reserve_in: u64,
+        reserve_out: u64,
+        is_x: bool,
+        amount_out: u64,
+        now: i64,
+    ) -> Result<(u64, u64)> {
+        let fee = self.swap_fee();
+        match self.pool_type {
+            PoolType::ConstantProduct => {
+                exact_out_input(reserve_in, reserve_out, amount_out, fee)
+            }
+            PoolType::StableSwap => stable_exact_out_input(
+                self.amp(now),
+                reserve_in,
+                reserve_out,
+                amount_out,
+                fee,
+            ),
+            PoolType::Launch => {
+                require!(amount_out < reserve_out, AmmError::Insufficientbalance);
+                let (curve_in, curve_out) = self.launch_reserves(is_x, reserve_in, reserve_out)?;
+                exact_out_input(curve_in, curve_out, amount_out, fee)
+            }
+        }
+    }
+
+    // what a launch-mode swap prices against: the real reserves plus the virtual ones
+    fn launch_reserves(
+        &self,
+        is_x: bool,
+        reserve_in: u64,
+        reserve_out: u64,
+    ) -> Result<(u64, u64)> {
+        let (virtual_in, virtual_out) = match is_x {
+            true => (self.launch.virtual_x, self.launch.virtual_y),
+            false => (self.launch.virtual_y, self.launch.virtual_x),
+        };
+        Ok((
+            reserve_in.checked_add(virtual_in).ok_or(AmmError::Overflow)?,
+            reserve_out.checked_add(virtual_out).ok_or(AmmError::Overflow)?,
+        ))
+    }
+
+    // part of a single-sided deposit of `amount` to swap before depositing both sides
+    pub fn zap_split(
+        &self,
+        reserve_in: u64,
+        reserve_out: u64,
+        amount: u64,
+        now: i64,
+    ) -> Result<u64> {
+        let fee = self.swap_fee();
+        match self.pool_type {
+            PoolType::ConstantProduct => zap_split(reserve_in, amount, fee),
+            PoolType::StableSwap => {
+                stable_zap_split(self.amp(now), reserve_in, reserve_out, amount, fee)
+            }
+            // there is no LP to deposit into before graduation
+            PoolType::Launch => err!(AmmError::InvalidPoolType),
+        }
+    }
 }
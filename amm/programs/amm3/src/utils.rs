@@ -0,0 +1,190 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token_2022::spl_token_2022::{
+    self,
+    extension::{
+        transfer_fee::TransferFeeConfig, BaseStateWithExtensions, ExtensionType,
+        StateWithExtensions,
+    },
+    state::Mint as MintState,
+};
+use anchor_lang::solana_program::program_pack::Pack;
+use anchor_lang::system_program::{
+    allocate, assign, create_account, transfer, Allocate, Assign, CreateAccount, Transfer,
+};
+use anchor_spl::token::spl_token;
+use anchor_spl::token_interface::{
+    close_account, initialize_account3, transfer_checked, CloseAccount, InitializeAccount3, Mint,
+    TransferChecked,
+};
+
+use crate::error::AmmError;
+
+// extensions that leave balances and transfers predictable for the pool
+const SUPPORTED_MINT_EXTENSIONS: [ExtensionType; 9] = [
+    ExtensionType::TransferFeeConfig,
+    ExtensionType::MintCloseAuthority,
+    ExtensionType::InterestBearingConfig,
+    ExtensionType::MetadataPointer,
+    ExtensionType::TokenMetadata,
+    ExtensionType::GroupPointer,
+    ExtensionType::TokenGroup,
+    ExtensionType::GroupMemberPointer,
+    ExtensionType::TokenGroupMember,
+];
+
+pub fn check_mint_extensions(mint: &AccountInfo) -> Result<()> {
+    if *mint.owner != anchor_spl::token_2022::ID {
+        return Ok(());
+    }
+    let data = mint.try_borrow_data()?;
+    let mint = StateWithExtensions::<MintState>::unpack(&data)?;
+    for extension in mint.get_extension_types()? {
+        require!(
+            SUPPORTED_MINT_EXTENSIONS.contains(&extension),
+            AmmError::UnsupportedMintExtension
+        );
+    }
+    Ok(())
+}
+
+// transfer fee withheld when sending `amount` of `mint`. Token-2022 takes it out of what
+// arrives, so the curve is only ever given what the vault receives and users are only promised
+// what reaches them: every leg is netted with this, or grossed up with `inverse_transfer_fee`
+pub fn transfer_fee(mint: &AccountInfo, amount: u64) -> Result<u64> {
+    if *mint.owner != anchor_spl::token_2022::ID {
+        return Ok(0);
+    }
+    let data = mint.try_borrow_data()?;
+    let mint = StateWithExtensions::<MintState>::unpack(&data)?;
+    match mint.get_extension::<TransferFeeConfig>() {
+        Ok(fee_config) => Ok(fee_config
+            .calculate_epoch_fee(Clock::get()?.epoch, amount)
+            .ok_or(AmmError::Overflow)?),
+        Err(_) => Ok(0),
+    }
+}
+
+// fee to add on top of `amount` so that `amount` arrives after the transfer fee
+pub fn inverse_transfer_fee(mint: &AccountInfo, amount: u64) -> Result<u64> {
+    if *mint.owner != anchor_spl::token_2022::ID {
+        return Ok(0);
+    }
+    let data = mint.try_borrow_data()?;
+    let mint = StateWithExtensions::<MintState>::unpack(&data)?;
+    match mint.get_extension::<TransferFeeConfig>() {
+        Ok(fee_config) => Ok(fee_config
+            .calculate_inverse_epoch_fee(Clock::get()?.epoch, amount)
+            .ok_or(AmmError::Overflow)?),
+        Err(_) => Ok(0),
+    }
+}
+
+// rejects instructions that land after the user's deadline, returns the current time
+pub fn check_expiry(expires_at: i64) -> Result<i64> {
+    let now = Clock::get()?.unix_timestamp;
+    require!(now <= expires_at, AmmError::TransactionExpired);
+    Ok(now)
+}
+
+pub fn transfer_tokens<'info>(
+    from: AccountInfo<'info>,
+    to: AccountInfo<'info>,
+    mint: &InterfaceAccount<'info, Mint>,
+    authority: AccountInfo<'info>,
+    token_program: AccountInfo<'info>,
+    amount: u64,
+    signer_seeds: &[&[&[u8]]],
+) -> Result<()> {
+    let accounts = TransferChecked {
+        from,
+        mint: mint.to_account_info(),
+        to,
+        authority,
+    };
+    let ctx = CpiContext::new_with_signer(token_program, accounts, signer_seeds);
+    transfer_checked(ctx, amount, mint.decimals)
+}
+
+pub fn is_native_mint(mint: &Pubkey) -> bool {
+    *mint == spl_token::native_mint::ID || *mint == spl_token_2022::native_mint::ID
+}
+
+// native SOL mode moves the wSOL side through a temporary account at [b"wsol", owner], never
+// the owner's own wSOL account: this opens it holding `amount` of the owner's lamports as wSOL.
+// Lamports already sitting at the address are kept, as anchor's `init` does
+pub fn wrap_native<'info>(
+    mint: &InterfaceAccount<'info, Mint>,
+    account: AccountInfo<'info>,
+    owner: AccountInfo<'info>,
+    system_program: AccountInfo<'info>,
+    token_program: AccountInfo<'info>,
+    amount: u64,
+) -> Result<()> {
+    require!(is_native_mint(&mint.key()), AmmError::InvalidToken);
+    let (_, bump) = Pubkey::find_program_address(&[b"wsol", owner.key.as_ref()], &crate::ID);
+    let seeds = &[&b"wsol"[..], owner.key.as_ref(), &[bump]];
+    let signer_seeds = &[&seeds[..]];
+    let space = spl_token::state::Account::LEN;
+    let lamports = Rent::get()?
+        .minimum_balance(space)
+        .checked_add(amount)
+        .ok_or(AmmError::Overflow)?;
+    let current = account.lamports();
+    if current == 0 {
+        let accounts = CreateAccount {
+            from: owner.clone(),
+            to: account.clone(),
+        };
+        create_account(
+            CpiContext::new_with_signer(system_program, accounts, signer_seeds),
+            lamports,
+            space as u64,
+            token_program.key,
+        )?;
+    } else {
+        if lamports > current {
+            let accounts = Transfer {
+                from: owner.clone(),
+                to: account.clone(),
+            };
+            transfer(
+                CpiContext::new(system_program.clone(), accounts),
+                lamports - current,
+            )?;
+        }
+        let accounts = Allocate {
+            account_to_allocate: account.clone(),
+        };
+        allocate(
+            CpiContext::new_with_signer(system_program.clone(), accounts, signer_seeds),
+            space as u64,
+        )?;
+        let accounts = Assign {
+            account_to_assign: account.clone(),
+        };
+        assign(
+            CpiContext::new_with_signer(system_program, accounts, signer_seeds),
+            token_program.key,
+        )?;
+    }
+    let accounts = InitializeAccount3 {
+        account,
+        mint: mint.to_account_info(),
+        authority: owner,
+    };
+    initialize_account3(CpiContext::new(token_program, accounts))
+}
+
+// closes the temporary wSOL account, so everything in it goes back to the owner as lamports
+pub fn unwrap_native<'info>(
+    account: AccountInfo<'info>,
+    owner: AccountInfo<'info>,
+    token_program: AccountInfo<'info>,
+) -> Result<()> {
+    let accounts = CloseAccount {
+        account,
+        destination: owner.clone(),
+        authority: owner,
+    };
+    close_account(CpiContext::new(token_program, accounts))
+}
@@ -0,0 +1,168 @@
+use amm3::events::{LiquidityAdded, LiquidityRemoved, ReferralPaid, Swapped};
+use anchor_lang::{AnchorDeserialize, Discriminator};
+use base64::{engine::general_purpose::STANDARD, Engine};
+
+// the trade and referral events amm3 writes to the transaction log with `emit!`
+pub enum AmmEvent {
+    Swapped(Swapped),
+    LiquidityAdded(LiquidityAdded),
+    LiquidityRemoved(LiquidityRemoved),
+    ReferralPaid(ReferralPaid),
+}
+
+// decodes one event from its discriminator-prefixed bytes, None for anything else
+pub fn decode_event(data: &[u8]) -> Option<AmmEvent> {
+    if let Some(body) = data.strip_prefix(Swapped::DISCRIMINATOR) {
+        return Swapped::try_from_slice(body).ok().map(AmmEvent::Swapped);
+    }
+    if let Some(body) = data.strip_prefix(LiquidityAdded::DISCRIMINATOR) {
+        return LiquidityAdded::try_from_slice(body)
+            .ok()
+            .map(AmmEvent::LiquidityAdded);
+    }
+    if let Some(body) = data.strip_prefix(LiquidityRemoved::DISCRIMINATOR) {
+        return LiquidityRemoved::try_from_slice(body)
+            .ok()
+            .map(AmmEvent::LiquidityRemoved);
+    }
+    if let Some(body) = data.strip_prefix(ReferralPaid::DISCRIMINATOR) {
+        return ReferralPaid::try_from_slice(body)
+            .ok()
+            .map(AmmEvent::ReferralPaid);
+    }
+    None
+}
+
+// every amm3 event in a transaction's log messages, in order. "Program data:" lines are
+// only trusted while amm3 itself is executing, so other programs cannot forge them
+pub fn decode_logs<S: AsRef<str>>(logs: &[S]) -> Vec<AmmEvent> {
+    let program_id = amm3::ID.to_string();
+    let mut stack: Vec<&str> = Vec::new();
+    let mut events = Vec::new();
+    for line in logs {
+        let line = line.as_ref();
+        if let Some(data) = line.strip_prefix("Program data: ") {
+            if stack.last() == Some(&program_id.as_str()) {
+                if let Some(event) = STANDARD.decode(data).ok().and_then(|d| decode_event(&d)) {
+                    events.push(event);
+                }
+            }
+            continue;
+        }
+        let mut words = line.split_whitespace();
+        if words.next() != Some("Program") {
+            continue;
+        }
+        match (words.next(), words.next()) {
+            (Some(id), Some("invoke")) => stack.push(id),
+            (Some(_), Some("success")) => {
+                stack.pop();
+            }
+            (Some(_), Some(status)) if status.starts_with("failed") => {
+                stack.pop();
+            }
+            _ => {}
+        }
+    }
+    events
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anchor_lang::prelude::Pubkey;
+    use anchor_lang::Event;
+
+    const TOKEN: &str = "TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA";
+    const ROUTER: &str = "JUP6LkbZbjS1jKKwapdHNy74zcZ3tLUZoi5QNyVTaV4";
+
+    fn swapped(amount_in: u64) -> Swapped {
+        Swapped {
+            config: Pubkey::new_unique(),
+            user: Pubkey::new_unique(),
+            is_x: true,
+            amount_in,
+            amount_out: 990,
+            fee: 3,
+            fee_bps: 30,
+            protocol_fee: 1,
+            reserve_x: 10_000,
+            reserve_y: 20_000,
+        }
+    }
+
+    // the line `emit!` writes through sol_log_data
+    fn data_line(event: &impl Event) -> String {
+        format!("Program data: {}", STANDARD.encode(event.data()))
+    }
+
+    fn amounts_in(events: &[AmmEvent]) -> Vec<u64> {
+        events
+            .iter()
+            .map(|event| match event {
+                AmmEvent::Swapped(event) => event.amount_in,
+                _ => panic!("expected a swap"),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn decodes_events_around_nested_cpis() {
+        let amm = amm3::ID.to_string();
+        let logs = vec![
+            format!("Program {ROUTER} invoke [1]"),
+            "Program log: Instruction: Route".to_string(),
+            format!("Program {amm} invoke [2]"),
+            "Program log: Instruction: Swap".to_string(),
+            format!("Program {TOKEN} invoke [3]"),
+            "Program log: Instruction: TransferChecked".to_string(),
+            format!("Program {TOKEN} consumed 6200 of 180000 compute units"),
+            format!("Program {TOKEN} success"),
+            data_line(&swapped(1_000)),
+            format!("Program {amm} consumed 40000 of 200000 compute units"),
+            format!("Program {amm} success"),
+            format!("Program {amm} invoke [2]"),
+            data_line(&swapped(2_000)),
+            format!("Program {amm} success"),
+            format!("Program {ROUTER} success"),
+        ];
+        assert_eq!(amounts_in(&decode_logs(&logs)), vec![1_000, 2_000]);
+    }
+
+    // another program can log the same bytes, only lines written while amm3 runs count
+    #[test]
+    fn ignores_data_logged_by_other_programs() {
+        let amm = amm3::ID.to_string();
+        let logs = vec![
+            format!("Program {ROUTER} invoke [1]"),
+            data_line(&swapped(1)),
+            format!("Program {amm} invoke [2]"),
+            format!("Program {TOKEN} invoke [3]"),
+            data_line(&swapped(2)),
+            format!("Program {TOKEN} success"),
+            data_line(&swapped(3)),
+            format!("Program {amm} success"),
+            data_line(&swapped(4)),
+            format!("Program {ROUTER} failed: custom program error: 0x1"),
+            data_line(&swapped(5)),
+        ];
+        assert_eq!(amounts_in(&decode_logs(&logs)), vec![3]);
+    }
+
+    #[test]
+    fn skips_truncated_and_garbage_payloads() {
+        let amm = amm3::ID.to_string();
+        let full = swapped(7).data();
+        let logs = vec![
+            format!("Program {amm} invoke [1]"),
+            format!("Program data: {}", STANDARD.encode(&full[..full.len() - 3])),
+            "Program data: not*base64!".to_string(),
+            "Program data: ".to_string(),
+            format!("Program data: {}", STANDARD.encode([0u8; 8])),
+            data_line(&swapped(7)),
+            format!("Program {amm} success"),
+        ];
+        assert_eq!(amounts_in(&decode_logs(&logs)), vec![7]);
+        assert!(decode_event(&full[..8]).is_none());
+    }
+}
@@ -0,0 +1,318 @@
+use amm3::state::{LaunchParams, PoolType};
+use anchor_lang::prelude::{Pubkey, System};
+use anchor_lang::solana_program::instruction::{AccountMeta, Instruction};
+use anchor_lang::{Id, InstructionData, ToAccountMetas};
+use anchor_spl::associated_token::{self, get_associated_token_address_with_program_id};
+
+use crate::pda;
+
+// every address a pool's instructions need, derived from its seed and mints
+#[derive(Clone, Copy, Debug)]
+pub struct PoolKeys {
+    pub seed: u64,
+    pub config: Pubkey,
+    pub mint_x: Pubkey,
+    pub mint_y: Pubkey,
+    pub mint_lp: Pubkey,
+    pub locked_lp: Pubkey,
+    pub vault_x: Pubkey,
+    pub vault_y: Pubkey,
+    pub treasury_x: Pubkey,
+    pub treasury_y: Pubkey,
+    pub oracle: Pubkey,
+    pub token_program: Pubkey,
+    pub token_program_x: Pubkey,
+    pub token_program_y: Pubkey,
+    // set for pools in allowlist mode, so user instructions carry the user's allowlist entry
+    pub allowlist: bool,
+}
+
+impl PoolKeys {
+    pub fn new(
+        seed: u64,
+        mint_x: Pubkey,
+        mint_y: Pubkey,
+        token_program_x: Pubkey,
+        token_program_y: Pubkey,
+    ) -> Self {
+        let config = pda::config_address(seed).0;
+        PoolKeys {
+            seed,
+            config,
+            mint_x,
+            mint_y,
+            mint_lp: pda::lp_mint_address(&config).0,
+            locked_lp: pda::locked_lp_address(&config).0,
+            vault_x: pda::vault_address(&config, &mint_x, &token_program_x),
+            vault_y: pda::vault_address(&config, &mint_y, &token_program_y),
+            treasury_x: pda::treasury_address(&config, &mint_x).0,
+            treasury_y: pda::treasury_address(&config, &mint_y).0,
+            oracle: pda::oracle_address(&config).0,
+            token_program: anchor_spl::token::ID,
+            token_program_x,
+            token_program_y,
+            allowlist: false,
+        }
+    }
+
+    // the user's token accounts for x, y and LP
+    pub fn user_accounts(&self, user: &Pubkey) -> (Pubkey, Pubkey, Pubkey) {
+        (
+            get_associated_token_address_with_program_id(user, &self.mint_x, &self.token_program_x),
+            get_associated_token_address_with_program_id(user, &self.mint_y, &self.token_program_y),
+            get_associated_token_address_with_program_id(user, &self.mint_lp, &self.token_program),
+        )
+    }
+
+    // the user's x and y accounts for a swap, deposit or withdraw; native SOL mode leaves out
+    // the wSOL side, which moves through the temporary account instead
+    fn user_token_accounts(
+        &self,
+        user: &Pubkey,
+        native_sol: bool,
+    ) -> (Option<Pubkey>, Option<Pubkey>) {
+        let (user_x, user_y, _) = self.user_accounts(user);
+        let is_native = |mint: &Pubkey| {
+            native_sol
+                && (*mint == anchor_spl::token::spl_token::native_mint::ID
+                    || *mint == anchor_spl::token_2022::spl_token_2022::native_mint::ID)
+        };
+        (
+            (!is_native(&self.mint_x)).then_some(user_x),
+            (!is_native(&self.mint_y)).then_some(user_y),
+        )
+    }
+
+    fn allowlist_entry(&self, user: &Pubkey) -> Option<Pubkey> {
+        self.allowlist
+            .then(|| pda::allowlist_address(&self.config, user).0)
+    }
+
+    fn native_account(&self, user: &Pubkey, native_sol: bool) -> Option<Pubkey> {
+        native_sol.then(|| pda::native_account_address(user).0)
+    }
+
+    // `pool_index_page` is the factory's pool count divided by `POOLS_PER_PAGE`
+    #[allow(clippy::too_many_arguments)]
+    pub fn initialize(
+        &self,
+        initializer: Pubkey,
+        lp_decimals: u8,
+        fee: u16,
+        protocol_fee: u16,
+        authority: Option<Pubkey>,
+        pool_type: PoolType,
+        amp: u64,
+        launch: Option<LaunchParams>,
+        pool_index_page: u64,
+    ) -> Instruction {
+        let accounts = amm3::accounts::Initialize {
+            initializer,
+            mint_x: self.mint_x,
+            mint_y: self.mint_y,
+            mint_lp: self.mint_lp,
+            locked_lp: self.locked_lp,
+            vault_x: self.vault_x,
+            vault_y: self.vault_y,
+            treasury_x: self.treasury_x,
+            treasury_y: self.treasury_y,
+            config: self.config,
+            oracle: self.oracle,
+            factory: pda::factory_address().0,
+            fee_tiers: pda::fee_tiers_address().0,
+            pair: pda::pair_address(&self.mint_x, &self.mint_y, fee).0,
+            pool_index: pda::pool_index_address(pool_index_page).0,
+            token_program: self.token_program,
+            token_program_x: self.token_program_x,
+            token_program_y: self.token_program_y,
+            associated_token_program: associated_token::ID,
+            system_program: System::id(),
+        };
+        let data = amm3::instruction::Initialize {
+            seed: self.seed,
+            lp_decimals,
+            fee,
+            protocol_fee,
+            authority,
+            pool_type,
+            amp,
+            launch,
+        };
+        self.instruction(accounts.to_account_metas(None), data.data())
+    }
+
+    pub fn deposit(
+        &self,
+        user: Pubkey,
+        amount: u64,
+        max_x: u64,
+        max_y: u64,
+        expires_at: i64,
+        native_sol: bool,
+    ) -> Instruction {
+        let (_, _, user_lp) = self.user_accounts(&user);
+        let (user_x, user_y) = self.user_token_accounts(&user, native_sol);
+        let accounts = amm3::accounts::Deposit {
+            user,
+            mint_x: self.mint_x,
+            mint_y: self.mint_y,
+            config: self.config,
+            mint_lp: self.mint_lp,
+            locked_lp: self.locked_lp,
+            vault_x: self.vault_x,
+            vault_y: self.vault_y,
+            user_x,
+            user_y,
+            user_lp,
+            oracle: self.oracle,
+            token_program: self.token_program,
+            token_program_x: self.token_program_x,
+            token_program_y: self.token_program_y,
+            system_program: System::id(),
+            associated_token_program: associated_token::ID,
+            allowlist_entry: self.allowlist_entry(&user),
+            native_account: self.native_account(&user, native_sol),
+        };
+        let data = amm3::instruction::Deposit {
+            amount,
+            max_x,
+            max_y,
+            expires_at,
+            native_sol,
+        };
+        self.instruction(accounts.to_account_metas(None), data.data())
+    }
+
+    pub fn withdraw(
+        &self,
+        user: Pubkey,
+        amount: u64,
+        min_x: u64,
+        min_y: u64,
+        expires_at: i64,
+        native_sol: bool,
+    ) -> Instruction {
+        let (_, _, user_lp) = self.user_accounts(&user);
+        let (user_x, user_y) = self.user_token_accounts(&user, native_sol);
+        let accounts = amm3::accounts::Withdraw {
+            user,
+            mint_x: self.mint_x,
+            mint_y: self.mint_y,
+            config: self.config,
+            mint_lp: self.mint_lp,
+            vault_x: self.vault_x,
+            vault_y: self.vault_y,
+            user_x,
+            user_y,
+            user_lp,
+            oracle: self.oracle,
+            system_program: System::id(),
+            token_program: self.token_program,
+            token_program_x: self.token_program_x,
+            token_program_y: self.token_program_y,
+            associated_token_program: associated_token::ID,
+            allowlist_entry: self.allowlist_entry(&user),
+            native_account: self.native_account(&user, native_sol),
+        };
+        let data = amm3::instruction::Withdraw {
+            amount,
+            min_x,
+            min_y,
+            expires_at,
+            native_sol,
+        };
+        self.instruction(accounts.to_account_metas(None), data.data())
+    }
+
+    // `referrer` is a token account for the input mint and the share of the swap fee it takes,
+    // in bps, at most the pool's `referral_fee`
+    #[allow(clippy::too_many_arguments)]
+    pub fn swap(
+        &self,
+        user: Pubkey,
+        is_x: bool,
+        amount: u64,
+        min: u64,
+        expires_at: i64,
+        native_sol: bool,
+        referrer: Option<(Pubkey, u16)>,
+    ) -> Instruction {
+        let data = amm3::instruction::Swap {
+            is_x,
+            amount,
+            min,
+            expires_at,
+            native_sol,
+            referral_bps: referrer.map_or(0, |(_, bps)| bps),
+        };
+        self.instruction(
+            self.swap_accounts(user, native_sol, referrer.map(|(account, _)| account)),
+            data.data(),
+        )
+    }
+
+    // `referrer` is a token account for the input mint and the share of the swap fee it takes,
+    // in bps, at most the pool's `referral_fee`
+    #[allow(clippy::too_many_arguments)]
+    pub fn swap_exact_out(
+        &self,
+        user: Pubkey,
+        is_x: bool,
+        amount_out: u64,
+        max_in: u64,
+        expires_at: i64,
+        native_sol: bool,
+        referrer: Option<(Pubkey, u16)>,
+    ) -> Instruction {
+        let data = amm3::instruction::SwapExactOut {
+            is_x,
+            amount_out,
+            max_in,
+            expires_at,
+            native_sol,
+            referral_bps: referrer.map_or(0, |(_, bps)| bps),
+        };
+        self.instruction(
+            self.swap_accounts(user, native_sol, referrer.map(|(account, _)| account)),
+            data.data(),
+        )
+    }
+
+    fn swap_accounts(
+        &self,
+        user: Pubkey,
+        native_sol: bool,
+        referrer: Option<Pubkey>,
+    ) -> Vec<AccountMeta> {
+        let (user_x, user_y) = self.user_token_accounts(&user, native_sol);
+        amm3::accounts::Swap {
+            user,
+            mint_x: self.mint_x,
+            mint_y: self.mint_y,
+            user_x,
+            user_y,
+            vault_x: self.vault_x,
+            vault_y: self.vault_y,
+            treasury_x: self.treasury_x,
+            treasury_y: self.treasury_y,
+            config: self.config,
+            oracle: self.oracle,
+            token_program_x: self.token_program_x,
+            token_program_y: self.token_program_y,
+            associated_token_program: associated_token::ID,
+            system_program: System::id(),
+            referrer,
+            allowlist_entry: self.allowlist_entry(&user),
+            native_account: self.native_account(&user, native_sol),
+        }
+        .to_account_metas(None)
+    }
+
+    fn instruction(&self, accounts: Vec<AccountMeta>, data: Vec<u8>) -> Instruction {
+        Instruction {
+            program_id: amm3::ID,
+            accounts,
+            data,
+        }
+    }
+}
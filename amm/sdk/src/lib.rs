@@ -0,0 +1,12 @@
+// Off-chain companion to the amm3 program: quotes that run the program's own math,
+// PDA derivation, instruction builders and event decoding.
+pub mod events;
+pub mod instructions;
+pub mod pda;
+pub mod quote;
+
+pub use amm3::state::{Config, LaunchParams, PoolType};
+pub use amm3::ID;
+pub use events::{decode_event, decode_logs, AmmEvent};
+pub use instructions::PoolKeys;
+pub use quote::{DepositQuote, Pool, SwapQuote, WithdrawQuote};
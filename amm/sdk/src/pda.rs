@@ -0,0 +1,165 @@
+use anchor_lang::prelude::Pubkey;
+use anchor_spl::associated_token::get_associated_token_address_with_program_id;
+
+pub fn config_address(seed: u64) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"config", seed.to_le_bytes().as_ref()], &amm3::ID)
+}
+
+pub fn lp_mint_address(config: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"lp", config.as_ref()], &amm3::ID)
+}
+
+// vaults are the config's associated token accounts
+pub fn vault_address(config: &Pubkey, mint: &Pubkey, token_program: &Pubkey) -> Pubkey {
+    get_associated_token_address_with_program_id(config, mint, token_program)
+}
+
+pub fn treasury_address(config: &Pubkey, mint: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"treasury", config.as_ref(), mint.as_ref()], &amm3::ID)
+}
+
+pub fn oracle_address(config: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"oracle", config.as_ref()], &amm3::ID)
+}
+
+pub fn locked_lp_address(config: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"locked", config.as_ref()], &amm3::ID)
+}
+
+pub fn factory_address() -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"factory"], &amm3::ID)
+}
+
+pub fn fee_tiers_address() -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"fee_tiers"], &amm3::ID)
+}
+
+// the registry entry for a pair at a fee tier, whichever order the mints are given in
+pub fn pair_address(mint_a: &Pubkey, mint_b: &Pubkey, fee: u16) -> (Pubkey, u8) {
+    let (mint_x, mint_y) = sort_mints(mint_a, mint_b);
+    Pubkey::find_program_address(
+        &[b"pair", mint_x.as_ref(), mint_y.as_ref(), fee.to_le_bytes().as_ref()],
+        &amm3::ID,
+    )
+}
+
+pub fn pool_index_address(page: u64) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"pool_index", page.to_le_bytes().as_ref()], &amm3::ID)
+}
+
+pub fn farm_address(config: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"farm", config.as_ref()], &amm3::ID)
+}
+
+pub fn user_stake_address(farm: &Pubkey, user: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"stake", farm.as_ref(), user.as_ref()], &amm3::ID)
+}
+
+pub fn order_address(config: &Pubkey, owner: &Pubkey, id: u64) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[b"order", config.as_ref(), owner.as_ref(), id.to_le_bytes().as_ref()],
+        &amm3::ID,
+    )
+}
+
+// pools take their mints in ascending order as x and y
+pub fn sort_mints<'a>(mint_a: &'a Pubkey, mint_b: &'a Pubkey) -> (&'a Pubkey, &'a Pubkey) {
+    match mint_a < mint_b {
+        true => (mint_a, mint_b),
+        false => (mint_b, mint_a),
+    }
+}
+
+pub fn allowlist_address(config: &Pubkey, wallet: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"allowlist", config.as_ref(), wallet.as_ref()], &amm3::ID)
+}
+
+// holds a vault for the LPs left in a pool closed by a forced exit
+pub fn exit_address(config: &Pubkey, mint: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"exit", config.as_ref(), mint.as_ref()], &amm3::ID)
+}
+
+// native SOL mode's temporary wSOL account, opened and closed within each instruction
+pub fn native_account_address(user: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"wsol", user.as_ref()], &amm3::ID)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // re-derives with the seeds and bump the program's account constraints check
+    fn check(address: (Pubkey, u8), seeds: &[&[u8]]) {
+        let bump = [address.1];
+        let seeds = [seeds, &[&bump[..]]].concat();
+        assert_eq!(
+            Pubkey::create_program_address(&seeds, &amm3::ID).unwrap(),
+            address.0
+        );
+    }
+
+    #[test]
+    fn addresses_match_the_program_seeds() {
+        let config = config_address(7);
+        check(config, &[b"config", &7u64.to_le_bytes()]);
+        let config = config.0;
+        let (mint, wallet) = (Pubkey::new_unique(), Pubkey::new_unique());
+        check(lp_mint_address(&config), &[b"lp", config.as_ref()]);
+        check(locked_lp_address(&config), &[b"locked", config.as_ref()]);
+        check(oracle_address(&config), &[b"oracle", config.as_ref()]);
+        check(
+            treasury_address(&config, &mint),
+            &[b"treasury", config.as_ref(), mint.as_ref()],
+        );
+        check(
+            exit_address(&config, &mint),
+            &[b"exit", config.as_ref(), mint.as_ref()],
+        );
+        check(
+            allowlist_address(&config, &wallet),
+            &[b"allowlist", config.as_ref(), wallet.as_ref()],
+        );
+        check(factory_address(), &[b"factory"]);
+        check(fee_tiers_address(), &[b"fee_tiers"]);
+        check(pool_index_address(3), &[b"pool_index", &3u64.to_le_bytes()]);
+        check(
+            order_address(&config, &wallet, 9),
+            &[
+                b"order",
+                config.as_ref(),
+                wallet.as_ref(),
+                &9u64.to_le_bytes(),
+            ],
+        );
+        let farm = farm_address(&config);
+        check(farm, &[b"farm", config.as_ref()]);
+        check(
+            user_stake_address(&farm.0, &wallet),
+            &[b"stake", farm.0.as_ref(), wallet.as_ref()],
+        );
+    }
+
+    #[test]
+    fn pairs_are_keyed_by_sorted_mints_and_fee() {
+        let (a, b) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let (x, y) = sort_mints(&a, &b);
+        assert!(x < y);
+        assert_eq!(pair_address(&a, &b, 30), pair_address(&b, &a, 30));
+        assert_ne!(pair_address(&a, &b, 30), pair_address(&a, &b, 100));
+        check(
+            pair_address(&a, &b, 30),
+            &[b"pair", x.as_ref(), y.as_ref(), &30u16.to_le_bytes()],
+        );
+    }
+
+    #[test]
+    fn vaults_are_the_config_associated_token_accounts() {
+        let (config, mint) = (config_address(7).0, Pubkey::new_unique());
+        let token_program = anchor_spl::token_2022::ID;
+        let (expected, _) = Pubkey::find_program_address(
+            &[config.as_ref(), token_program.as_ref(), mint.as_ref()],
+            &anchor_spl::associated_token::ID,
+        );
+        assert_eq!(vault_address(&config, &mint, &token_program), expected);
+    }
+}
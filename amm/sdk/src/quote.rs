@@ -0,0 +1,420 @@
+use amm3::constants::MINIMUM_LIQUIDITY;
+use amm3::curve::{deposit_amounts, initial_liquidity, withdraw_amounts};
+use amm3::error::AmmError;
+use amm3::state::Config;
+use anchor_lang::prelude::*;
+use anchor_spl::token_2022::spl_token_2022::{
+    extension::{transfer_fee::TransferFeeConfig, BaseStateWithExtensions, StateWithExtensions},
+    state::Mint as MintState,
+};
+use anchor_spl::token_interface::{Mint, TokenAccount};
+
+// what a swap takes from and pays to the user, both after transfer fees
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SwapQuote {
+    pub amount_in: u64,
+    pub amount_out: u64,
+    pub fee: u64,
+    pub protocol_fee: u64,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct DepositQuote {
+    pub amount_x: u64,
+    pub amount_y: u64,
+    pub lp_amount: u64,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct WithdrawQuote {
+    pub amount_x: u64,
+    pub amount_y: u64,
+}
+
+// a pool as the program sees it: its config, the vault balances and the LP supply
+#[derive(Clone)]
+pub struct Pool {
+    pub config: Config,
+    pub reserve_x: u64,
+    pub reserve_y: u64,
+    pub lp_supply: u64,
+    pub transfer_fee_x: Option<TransferFeeConfig>,
+    pub transfer_fee_y: Option<TransferFeeConfig>,
+    pub epoch: u64,
+}
+
+impl Pool {
+    // from raw account data of the config, both vaults and the LP mint
+    pub fn from_accounts(
+        config: &[u8],
+        vault_x: &[u8],
+        vault_y: &[u8],
+        mint_lp: &[u8],
+    ) -> Result<Self> {
+        Ok(Pool {
+            config: Config::try_deserialize(&mut &config[..])?,
+            reserve_x: TokenAccount::try_deserialize(&mut &vault_x[..])?.amount,
+            reserve_y: TokenAccount::try_deserialize(&mut &vault_y[..])?.amount,
+            lp_supply: Mint::try_deserialize(&mut &mint_lp[..])?.supply,
+            transfer_fee_x: None,
+            transfer_fee_y: None,
+            epoch: 0,
+        })
+    }
+
+    // picks up Token-2022 transfer fees from the raw mint accounts, as of `epoch`
+    pub fn with_mints(mut self, mint_x: &[u8], mint_y: &[u8], epoch: u64) -> Result<Self> {
+        self.transfer_fee_x = transfer_fee_config(mint_x)?;
+        self.transfer_fee_y = transfer_fee_config(mint_y)?;
+        self.epoch = epoch;
+        Ok(self)
+    }
+
+    // swaps are quoted at the fee the program would charge in `slot`
+    pub fn quote_swap(&self, is_x: bool, amount_in: u64, now: i64, slot: u64) -> Result<SwapQuote> {
+        self.config.check_unlocked()?;
+        require!(amount_in > 0, AmmError::InvalidAmount);
+        let config = self.config_at(slot);
+        let received = amount_in - self.transfer_fee(is_x, amount_in)?;
+        let res = config.quote_exact_in(self.reserve_x, self.reserve_y, is_x, received, now)?;
+        Ok(SwapQuote {
+            amount_in,
+            amount_out: res.withdraw - self.transfer_fee(!is_x, res.withdraw)?,
+            fee: res.fee,
+            protocol_fee: config.protocol_fee_amount(res.fee)?,
+        })
+    }
+
+    pub fn quote_swap_exact_out(
+        &self,
+        is_x: bool,
+        amount_out: u64,
+        now: i64,
+        slot: u64,
+    ) -> Result<SwapQuote> {
+        self.config.check_unlocked()?;
+        require!(amount_out > 0, AmmError::InvalidAmount);
+        let (reserve_in, reserve_out) = match is_x {
+            true => (self.reserve_x, self.reserve_y),
+            false => (self.reserve_y, self.reserve_x),
+        };
+        let config = self.config_at(slot);
+        let withdraw = amount_out + self.inverse_transfer_fee(!is_x, amount_out)?;
+        let (received, fee) = config.quote_exact_out(reserve_in, reserve_out, is_x, withdraw, now)?;
+        Ok(SwapQuote {
+            amount_in: received + self.inverse_transfer_fee(is_x, received)?,
+            amount_out,
+            fee,
+            protocol_fee: config.protocol_fee_amount(fee)?,
+        })
+    }
+
+    // the config with a dynamic fee decayed to `slot`, as a swap refreshes it on-chain
+    fn config_at(&self, slot: u64) -> Config {
+        let mut config = self.config.clone();
+        config.refresh_dynamic_fee(slot);
+        config
+    }
+
+    // what the user pays for `lp_amount` LP in an existing pool
+    pub fn quote_deposit(&self, lp_amount: u64) -> Result<DepositQuote> {
+        self.config.check_unlocked()?;
+        require!(lp_amount != 0, AmmError::InvalidAmount);
+        require!(self.lp_supply != 0, AmmError::NoLiquidityInPool);
+        let (x, y) = deposit_amounts(self.reserve_x, self.reserve_y, self.lp_supply, lp_amount)?;
+        Ok(DepositQuote {
+            amount_x: x + self.inverse_transfer_fee(true, x)?,
+            amount_y: y + self.inverse_transfer_fee(false, y)?,
+            lp_amount,
+        })
+    }
+
+    // LP the user gets for seeding an empty pool, after the locked minimum liquidity
+    pub fn quote_initial_deposit(&self, amount_x: u64, amount_y: u64) -> Result<DepositQuote> {
+        self.config.check_unlocked()?;
+        let x = amount_x - self.transfer_fee(true, amount_x)?;
+        let y = amount_y - self.transfer_fee(false, amount_y)?;
+        let liquidity = initial_liquidity(
+            x,
+            y,
+            self.config.decimals_x,
+            self.config.decimals_y,
+            self.config.lp_decimals,
+        )?;
+        require!(
+            liquidity > MINIMUM_LIQUIDITY,
+            AmmError::LiquidityLessThanMinium
+        );
+        Ok(DepositQuote {
+            amount_x,
+            amount_y,
+            lp_amount: liquidity - MINIMUM_LIQUIDITY,
+        })
+    }
+
+    // what reaches the user for burning `lp_amount` LP
+    pub fn quote_withdraw(&self, lp_amount: u64) -> Result<WithdrawQuote> {
+        self.config.check_unlocked()?;
+        require!(lp_amount > 0, AmmError::InvalidAmount);
+        let (x, y) = withdraw_amounts(self.reserve_x, self.reserve_y, self.lp_supply, lp_amount)?;
+        Ok(WithdrawQuote {
+            amount_x: x - self.transfer_fee(true, x)?,
+            amount_y: y - self.transfer_fee(false, y)?,
+        })
+    }
+
+    fn transfer_fee(&self, is_x: bool, amount: u64) -> Result<u64> {
+        match self.fee_config(is_x) {
+            Some(fee_config) => Ok(fee_config
+                .calculate_epoch_fee(self.epoch, amount)
+                .ok_or(AmmError::Overflow)?),
+            None => Ok(0),
+        }
+    }
+
+    fn inverse_transfer_fee(&self, is_x: bool, amount: u64) -> Result<u64> {
+        match self.fee_config(is_x) {
+            Some(fee_config) => Ok(fee_config
+                .calculate_inverse_epoch_fee(self.epoch, amount)
+                .ok_or(AmmError::Overflow)?),
+            None => Ok(0),
+        }
+    }
+
+    fn fee_config(&self, is_x: bool) -> Option<&TransferFeeConfig> {
+        match is_x {
+            true => self.transfer_fee_x.as_ref(),
+            false => self.transfer_fee_y.as_ref(),
+        }
+    }
+}
+
+fn transfer_fee_config(mint: &[u8]) -> Result<Option<TransferFeeConfig>> {
+    let mint = StateWithExtensions::<MintState>::unpack(mint)?;
+    Ok(mint.get_extension::<TransferFeeConfig>().ok().copied())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use amm3::constants::VOLATILITY_HALF_LIFE;
+    use amm3::math::stable_compute_d;
+    use amm3::state::{DynamicFee, LaunchParams, PoolType};
+    use anchor_lang::solana_program::program_pack::Pack;
+    use anchor_spl::token_2022::spl_token_2022::extension::{
+        BaseStateWithExtensionsMut, ExtensionType, StateWithExtensionsMut,
+    };
+
+    const FEE: u16 = 30;
+    const TRANSFER_FEE_BPS: u16 = 100;
+
+    fn config(pool_type: PoolType) -> Config {
+        Config {
+            seed: 1,
+            authority: None,
+            initializer: Pubkey::default(),
+            mint_x: Pubkey::new_unique(),
+            mint_y: Pubkey::new_unique(),
+            decimals_x: 6,
+            decimals_y: 6,
+            lp_decimals: 6,
+            fee: FEE,
+            protocol_fee: 2_000,
+            referral_fee: 0,
+            locked: false,
+            allowlist: false,
+            flash_fee: 9,
+            flash_loan_amount: 0,
+            flash_loan_is_x: false,
+            pool_type,
+            amp_initial: 100,
+            amp_target: 100,
+            amp_ramp_start: 0,
+            amp_ramp_end: 0,
+            launch: LaunchParams::default(),
+            dynamic_fee: DynamicFee::default(),
+            close_after: 0,
+            open_orders: 0,
+            config_bump: 255,
+            lp_bump: 255,
+            locked_lp_bump: 255,
+            treasury_x_bump: 255,
+            treasury_y_bump: 255,
+            oracle_bump: 255,
+        }
+    }
+
+    fn pool(pool_type: PoolType) -> Pool {
+        Pool {
+            config: config(pool_type),
+            reserve_x: 1_000_000_000,
+            reserve_y: 2_000_000_000,
+            lp_supply: 1_414_213_562,
+            transfer_fee_x: None,
+            transfer_fee_y: None,
+            epoch: 0,
+        }
+    }
+
+    // raw Token-2022 mint data, with a transfer fee of `fee_bps` when given
+    fn mint_data(fee_bps: Option<u16>) -> Vec<u8> {
+        let base = MintState {
+            decimals: 6,
+            is_initialized: true,
+            ..Default::default()
+        };
+        let fee_bps = match fee_bps {
+            Some(fee_bps) => fee_bps,
+            None => {
+                let mut data = vec![0; MintState::LEN];
+                MintState::pack(base, &mut data).unwrap();
+                return data;
+            }
+        };
+        let space = ExtensionType::try_calculate_account_len::<MintState>(&[
+            ExtensionType::TransferFeeConfig,
+        ])
+        .unwrap();
+        let mut data = vec![0; space];
+        let mut state =
+            StateWithExtensionsMut::<MintState>::unpack_uninitialized(&mut data).unwrap();
+        let extension = state.init_extension::<TransferFeeConfig>(true).unwrap();
+        extension.newer_transfer_fee.transfer_fee_basis_points = fee_bps.into();
+        extension.newer_transfer_fee.maximum_fee = u64::MAX.into();
+        extension.older_transfer_fee = extension.newer_transfer_fee;
+        state.base = base;
+        state.pack_base();
+        state.init_account_type().unwrap();
+        data
+    }
+
+    // what Token-2022 withholds on a transfer of `amount` at TRANSFER_FEE_BPS
+    fn withheld(amount: u64) -> u64 {
+        ((amount as u128 * TRANSFER_FEE_BPS as u128).div_ceil(10_000)) as u64
+    }
+
+    // 1_000_000 x in: fee ceil(1e6 * 30 / 1e4) = 3_000, out 2e9 * 997_000 / (1e9 + 997_000)
+    #[test]
+    fn constant_product_quotes_match_the_curve() {
+        let pool = pool(PoolType::ConstantProduct);
+        let quote = pool.quote_swap(true, 1_000_000, 0, 0).unwrap();
+        assert_eq!((quote.amount_out, quote.fee), (1_992_013, 3_000));
+        assert_eq!(quote.protocol_fee, 600);
+
+        // y in for 1_000_000 x out: ceil(2e9 * 1e6 / 999_000_000) = 2_002_003 reaches the curve,
+        // grossed up by the fee to ceil(2_002_003 * 1e4 / 9_970)
+        let quote = pool.quote_swap_exact_out(false, 1_000_000, 0, 0).unwrap();
+        assert_eq!((quote.amount_in, quote.fee), (2_008_028, 6_025));
+    }
+
+    #[test]
+    fn stable_quotes_keep_the_invariant() {
+        let mut pool = pool(PoolType::StableSwap);
+        pool.reserve_y = pool.reserve_x;
+        let d = stable_compute_d(100, pool.reserve_x, pool.reserve_y).unwrap();
+
+        let quote = pool.quote_swap(false, 1_000_000, 0, 0).unwrap();
+        assert_eq!(quote.fee, 3_000);
+        // near the peg the output is close to the input net of the fee, never above it
+        assert!(quote.amount_out < 1_000_000 - quote.fee);
+        assert!(quote.amount_out > 990_000);
+        let after = stable_compute_d(
+            100,
+            pool.reserve_x - quote.amount_out,
+            pool.reserve_y + 1_000_000,
+        )
+        .unwrap();
+        assert!(after >= d);
+
+        let quote = pool.quote_swap_exact_out(true, 1_000_000, 0, 0).unwrap();
+        assert!(quote.amount_in > 1_000_000 + quote.fee);
+        assert_eq!(
+            quote.fee,
+            (quote.amount_in as u128 * FEE as u128).div_ceil(10_000) as u64
+        );
+        let after = stable_compute_d(
+            100,
+            pool.reserve_x + quote.amount_in,
+            pool.reserve_y - 1_000_000,
+        )
+        .unwrap();
+        assert!(after >= d);
+    }
+
+    // 1_000_000 LP out of 1_414_213_562 is 1/1414.2 of each reserve,
+    // rounded up when paid in and down when paid out
+    #[test]
+    fn liquidity_quotes_match_the_curve() {
+        let pool = pool(PoolType::ConstantProduct);
+        let quote = pool.quote_deposit(1_000_000).unwrap();
+        assert_eq!((quote.amount_x, quote.amount_y), (707_107, 1_414_214));
+
+        let quote = pool.quote_withdraw(1_000_000).unwrap();
+        assert_eq!((quote.amount_x, quote.amount_y), (707_106, 1_414_213));
+
+        // sqrt(1e6 * 4e6) less the locked minimum
+        let quote = pool.quote_initial_deposit(1_000_000, 4_000_000).unwrap();
+        assert_eq!(quote.lp_amount, 2_000_000 - MINIMUM_LIQUIDITY);
+    }
+
+    // the curve sees the input net of the transfer fee, the user the output net of it
+    #[test]
+    fn transfer_fee_mints_are_netted_out() {
+        let pool = pool(PoolType::ConstantProduct)
+            .with_mints(&mint_data(Some(TRANSFER_FEE_BPS)), &mint_data(None), 0)
+            .unwrap();
+        assert!(pool.transfer_fee_y.is_none());
+
+        // 990_000 reaches the vault
+        let quote = pool.quote_swap(true, 1_000_000, 0, 0).unwrap();
+        assert_eq!(quote.amount_out, 1_972_113);
+
+        // 498_251 leaves the vault, 4_983 of it is withheld
+        let quote = pool.quote_swap(false, 1_000_000, 0, 0).unwrap();
+        assert_eq!(quote.amount_out, 493_268);
+
+        // exact out grosses the input up so the vault still receives the 501_757 the curve needs
+        let quote = pool.quote_swap_exact_out(true, 1_000_000, 0, 0).unwrap();
+        assert!(quote.amount_in - withheld(quote.amount_in) >= 501_757);
+        assert!(quote.amount_in - 1 - withheld(quote.amount_in - 1) < 501_757);
+
+        // 707_106 less 7_072 withheld
+        let quote = pool.quote_withdraw(1_000_000).unwrap();
+        assert_eq!((quote.amount_x, quote.amount_y), (700_034, 1_414_213));
+    }
+
+    // the lifted fee decays back to the tier fee the same way a swap refreshes it
+    #[test]
+    fn dynamic_fee_quotes_decay_with_the_slot() {
+        let mut pool = pool(PoolType::ConstantProduct);
+        pool.config.dynamic_fee = DynamicFee {
+            enabled: true,
+            max_fee: 100,
+            volatility: 500,
+            last_slot: 1_000,
+            fee: 80,
+        };
+        // 30 + 500 * 1_000 / 10_000
+        let quote = pool.quote_swap(true, 1_000_000, 0, 1_000).unwrap();
+        assert_eq!((quote.amount_out, quote.fee), (1_982_033, 8_000));
+
+        // one half-life later the volatility is 250, the fee 55
+        let quote = pool
+            .quote_swap_exact_out(true, 1_000_000, 0, 1_000 + VOLATILITY_HALF_LIFE)
+            .unwrap();
+        assert_eq!((quote.amount_in, quote.fee), (503_018, 2_767));
+
+        let quote = pool.quote_swap(true, 1_000_000, 0, 1_000_000).unwrap();
+        assert_eq!((quote.amount_out, quote.fee), (1_992_013, 3_000));
+        // quoting leaves the pool's own state alone
+        assert_eq!(pool.config.dynamic_fee.fee, 80);
+    }
+
+    #[test]
+    fn locked_pools_do_not_quote() {
+        let mut pool = pool(PoolType::ConstantProduct);
+        pool.config.locked = true;
+        assert!(pool.quote_swap(true, 1_000_000, 0, 0).is_err());
+        assert!(pool.quote_withdraw(1_000_000).is_err());
+    }
+}
@@ -13,6 +13,13 @@ import {
   getAccount,
   getMint,
   mintTo,
+  TOKEN_2022_PROGRAM_ID,
+  ExtensionType,
+  getMintLen,
+  createInitializeTransferFeeConfigInstruction,
+  createInitializeMintInstruction,
+  NATIVE_MINT,
+  createWrappedNativeAccount,
 } from "@solana/spl-token";
 import { Amm3 } from "../target/types/amm3";
 import { expect } from "chai";
@@ -33,16 +40,38 @@ describe("initialize AMM", () => {
   // Config seed
   const seed = new BN(42);
 
+  // deadline a few minutes out for user instructions
+  const expiresAt = () => new BN(Math.floor(Date.now() / 1000) + 600);
+
+  // index page a newly initialized pool is listed on
+  const nextPoolIndex = async () => {
+    const [factoryPda] = PublicKey.findProgramAddressSync([Buffer.from("factory")], program.programId);
+    const factory = await program.account.factory.fetchNullable(factoryPda);
+    const page = factory ? Math.floor(factory.poolCount.toNumber() / 64) : 0;
+    return PublicKey.findProgramAddressSync(
+      [Buffer.from("pool_index"), new BN(page).toArrayLike(Buffer, "le", 8)],
+      program.programId
+    )[0];
+  };
+
   //  (PDAs)
   let configPda: PublicKey;
   let mintLpPda: PublicKey;
   let vaultXPda: PublicKey;
   let vaultYPda: PublicKey;
+  let treasuryXPda: PublicKey;
+  let treasuryYPda: PublicKey;
+  let oraclePda: PublicKey;
+  let lockedLpPda: PublicKey;
 
   it("Initializes AMM config", async () => {
     // 1. Mint token X i Y
     mintX = await createMint(provider.connection, initializer.payer, initializer.publicKey, null, 6);
     mintY = await createMint(provider.connection, initializer.payer, initializer.publicKey, null, 6);
+    // pools take their mints in canonical order
+    if (Buffer.compare(mintX.toBuffer(), mintY.toBuffer()) > 0) {
+      [mintX, mintY] = [mintY, mintX];
+    }
 
     // 2.PDA derivation
     [configPda] = await PublicKey.findProgramAddressSync(
@@ -55,21 +84,60 @@ describe("initialize AMM", () => {
       program.programId
     );
 
+    [oraclePda] = PublicKey.findProgramAddressSync(
+      [Buffer.from("oracle"), configPda.toBuffer()],
+      program.programId
+    );
+
+    [lockedLpPda] = PublicKey.findProgramAddressSync(
+      [Buffer.from("locked"), configPda.toBuffer()],
+      program.programId
+    );
+
     vaultXPda = getAssociatedTokenAddressSync(mintX, configPda, true);
     vaultYPda = getAssociatedTokenAddressSync(mintY, configPda, true);
 
-    // 3. Initialize call
+    [treasuryXPda] = PublicKey.findProgramAddressSync(
+      [Buffer.from("treasury"), configPda.toBuffer(), mintX.toBuffer()],
+      program.programId
+    );
+    [treasuryYPda] = PublicKey.findProgramAddressSync(
+      [Buffer.from("treasury"), configPda.toBuffer(), mintY.toBuffer()],
+      program.programId
+    );
+
+    // 3. governance lists the fee tiers pools may be created at
+    const [programData] = PublicKey.findProgramAddressSync(
+      [program.programId.toBuffer()],
+      new PublicKey("BPFLoaderUpgradeab1e11111111111111111111111")
+    );
+    await program.methods
+      .initFeeTiers(initializer.publicKey)
+      .accounts({ payer: initializer.publicKey, program: program.programId, programData })
+      .rpc();
+    for (const tier of [4, 30, 100]) {
+      await program.methods.addFeeTier(tier).accounts({ authority: initializer.publicKey }).rpc();
+    }
+
+    // 4. Initialize call
     await program.methods
-      .initialize(seed, 30, null) // fee = 30, authority = null
+      .initialize(seed, 6, 30, 2_000, initializer.publicKey, { constantProduct: {} }, new BN(0), null) // fee = 30, protocol share = 20% of the fee
       .accounts({
         initializer: initializer.publicKey,
+        poolIndex: await nextPoolIndex(),
         mintX,
         mintY,
         mintLp: mintLpPda,
+        lockedLp: lockedLpPda,
         vaultX: vaultXPda,
         vaultY: vaultYPda,
+        treasuryX: treasuryXPda,
+        treasuryY: treasuryYPda,
         config: configPda,
+        oracle: oraclePda,
         tokenProgram: anchor.utils.token.TOKEN_PROGRAM_ID,
+        tokenProgramX: anchor.utils.token.TOKEN_PROGRAM_ID,
+        tokenProgramY: anchor.utils.token.TOKEN_PROGRAM_ID,
         associatedTokenProgram: anchor.utils.token.ASSOCIATED_PROGRAM_ID,
         systemProgram: SystemProgram.programId,
       })
@@ -138,30 +206,40 @@ describe("initialize AMM", () => {
       2_000_000
     );
 
+    // the first deposit's LP is sqrt(x * y), this is only the least accepted
     const amountLp = new BN(1_000);
 
     await program.methods
-      .deposit(amountLp, new BN(1_000_000), new BN(2_000_000))
+      .deposit(amountLp, new BN(1_000_000), new BN(2_000_000), expiresAt(), false)
       .accounts({
         user: initializer.publicKey,
         mintX,
         mintY,
         mintLp: mintLpPda,
+        lockedLp: lockedLpPda,
         vaultX: vaultXPda,
         vaultY: vaultYPda,
         userX: userXPda,
         userY: userYPda,
         userLp: userLpPda,
         config: configPda,
+        oracle: oraclePda,
         tokenProgram: anchor.utils.token.TOKEN_PROGRAM_ID,
+        tokenProgramX: anchor.utils.token.TOKEN_PROGRAM_ID,
+        tokenProgramY: anchor.utils.token.TOKEN_PROGRAM_ID,
         associatedTokenProgram: anchor.utils.token.ASSOCIATED_PROGRAM_ID,
         systemProgram: SystemProgram.programId,
+        allowlistEntry: null,
+        nativeAccount: null,
       })
       .rpc();
 
     const userLp = await getAccount(provider.connection, userLpPda);
     console.log("✅ LP minted to user:", userLp.amount);
-    expect(Number(userLp.amount)).to.equal(amountLp.toNumber());
+    // sqrt(1_000_000 * 2_000_000) less the 1_000 locked for good
+    expect(Number(userLp.amount)).to.equal(1_413_213);
+    const lockedLp = await getAccount(provider.connection, lockedLpPda);
+    expect(Number(lockedLp.amount)).to.equal(1_000);
   });
 
   it("Swaps tokens via AMM", async () => {
@@ -180,8 +258,8 @@ describe("initialize AMM", () => {
     const amountX = new BN(500_000);
     const minY = new BN(1);
 
-    await program.methods
-      .swap(true, amountX, minY)
+    const tx = await program.methods
+      .swap(true, amountX, minY, expiresAt(), false, 0)
       .accounts({
         user: initializer.publicKey,
         mintX,
@@ -190,12 +268,23 @@ describe("initialize AMM", () => {
         vaultY: vaultYPda,
         userX: userXPda,
         userY: userYPda,
+        treasuryX: treasuryXPda,
+        treasuryY: treasuryYPda,
         config: configPda,
-        tokenProgram: anchor.utils.token.TOKEN_PROGRAM_ID,
+        oracle: oraclePda,
+        tokenProgramX: anchor.utils.token.TOKEN_PROGRAM_ID,
+        tokenProgramY: anchor.utils.token.TOKEN_PROGRAM_ID,
         associatedTokenProgram: anchor.utils.token.ASSOCIATED_PROGRAM_ID,
         systemProgram: SystemProgram.programId,
+        referrer: null,
+        allowlistEntry: null,
+        nativeAccount: null,
       })
-      .rpc();
+      .rpc({ commitment: "confirmed" });
+
+    const treasuryX = await getAccount(provider.connection, treasuryXPda);
+    console.log("✅ Protocol fees accrued in X:", treasuryX.amount);
+    expect(Number(treasuryX.amount)).to.be.greaterThan(0);
 
     const userXPost = await getAccount(provider.connection, userXPda);
     const userYPost = await getAccount(provider.connection, userYPda);
@@ -205,11 +294,439 @@ describe("initialize AMM", () => {
 
     expect(Number(userXPost.amount)).to.be.lessThan(1_200_000);
 
+    const txInfo = await provider.connection.getTransaction(tx, {
+      commitment: "confirmed",
+      maxSupportedTransactionVersion: 0,
+    });
+    const parser = new anchor.EventParser(program.programId, program.coder);
+    const swapped = [...parser.parseLogs(txInfo.meta.logMessages)].find((e) => e.name === "swapped");
+    expect(swapped.data.amountIn.toNumber()).to.equal(500_000);
+    expect(swapped.data.reserveX.toString()).to.equal((await getAccount(provider.connection, vaultXPda)).amount.toString());
+
+  });
+
+  it("Swaps for an exact output amount", async () => {
+    const userYPre = await getAccount(provider.connection, userYPda);
+    const amountOutY = new BN(10_000);
+
+    await program.methods
+      .swapExactOut(true, amountOutY, new BN(100_000), expiresAt(), false, 0)
+      .accounts({
+        user: initializer.publicKey,
+        mintX,
+        mintY,
+        vaultX: vaultXPda,
+        vaultY: vaultYPda,
+        userX: userXPda,
+        userY: userYPda,
+        treasuryX: treasuryXPda,
+        treasuryY: treasuryYPda,
+        config: configPda,
+        oracle: oraclePda,
+        tokenProgramX: anchor.utils.token.TOKEN_PROGRAM_ID,
+        tokenProgramY: anchor.utils.token.TOKEN_PROGRAM_ID,
+        associatedTokenProgram: anchor.utils.token.ASSOCIATED_PROGRAM_ID,
+        systemProgram: SystemProgram.programId,
+        referrer: null,
+        allowlistEntry: null,
+        nativeAccount: null,
+      })
+      .rpc();
+
+    const userYPost = await getAccount(provider.connection, userYPda);
+    expect(Number(userYPost.amount - userYPre.amount)).to.equal(amountOutY.toNumber());
+  });
+
+  it("Pays the referrer a share of the swap fee", async () => {
+    await program.methods
+      .updateReferralFee(1_000) // 10% of the fee
+      .accounts({ authority: initializer.publicKey, config: configPda })
+      .rpc();
+
+    const partner = Keypair.generate();
+    const referrer = await getOrCreateAssociatedTokenAccount(
+      provider.connection,
+      initializer.payer,
+      mintX,
+      partner.publicKey
+    );
+
+    const swapAccounts = (referrer: PublicKey) => ({
+      user: initializer.publicKey,
+      mintX,
+      mintY,
+      vaultX: vaultXPda,
+      vaultY: vaultYPda,
+      userX: userXPda,
+      userY: userYPda,
+      treasuryX: treasuryXPda,
+      treasuryY: treasuryYPda,
+      config: configPda,
+      oracle: oraclePda,
+      tokenProgramX: anchor.utils.token.TOKEN_PROGRAM_ID,
+      tokenProgramY: anchor.utils.token.TOKEN_PROGRAM_ID,
+      associatedTokenProgram: anchor.utils.token.ASSOCIATED_PROGRAM_ID,
+      systemProgram: SystemProgram.programId,
+      referrer,
+      allowlistEntry: null,
+      nativeAccount: null,
+    });
+
+    // the front-end cannot ask for more than the pool's cap
+    try {
+      await program.methods
+        .swap(true, new BN(100_000), new BN(1), expiresAt(), false, 1_001)
+        .accounts(swapAccounts(referrer.address))
+        .rpc();
+      expect.fail("referral share above the cap should fail");
+    } catch (err) {
+      expect(err.error.errorCode.code).to.equal("ReferralFeeTooHigh");
+    }
+
+    // nor can the trader refer themselves
+    try {
+      await program.methods
+        .swap(true, new BN(100_000), new BN(1), expiresAt(), false, 1_000)
+        .accounts(swapAccounts(userXPda))
+        .rpc();
+      expect.fail("self-referral should fail");
+    } catch (err) {
+      expect(err.error.errorCode.code).to.equal("SelfReferral");
+    }
+
+    await program.methods
+      .swap(true, new BN(100_000), new BN(1), expiresAt(), false, 1_000)
+      .accounts(swapAccounts(referrer.address))
+      .rpc();
+
+    // 0.3% of 100_000 is 300, a tenth of it goes to the referrer
+    const paid = await getAccount(provider.connection, referrer.address);
+    expect(Number(paid.amount)).to.equal(30);
+  });
+
+  it("Fills a limit order by keeper and cancels one that is out of range", async () => {
+    const orderPda = (id: number) =>
+      PublicKey.findProgramAddressSync(
+        [
+          Buffer.from("order"),
+          configPda.toBuffer(),
+          initializer.publicKey.toBuffer(),
+          new BN(id).toArrayLike(Buffer, "le", 8),
+        ],
+        program.programId
+      )[0];
+    const orderAccounts = (id: number) => ({
+      mintIn: mintX,
+      config: configPda,
+      order: orderPda(id),
+      tokenProgramIn: anchor.utils.token.TOKEN_PROGRAM_ID,
+      associatedTokenProgram: anchor.utils.token.ASSOCIATED_PROGRAM_ID,
+      systemProgram: SystemProgram.programId,
+    });
+
+    // sell 10_000 X for at least 1 Y, tipping the keeper 0.001 SOL
+    await program.methods
+      .placeOrder(new BN(1), new BN(10_000), new BN(1), new BN(1_000_000), expiresAt())
+      .accounts({ ...orderAccounts(1), mintOut: mintY, owner: initializer.publicKey })
+      .rpc();
+    const userYPre = await getAccount(provider.connection, userYPda);
+    await program.methods
+      .fillOrder()
+      .accounts({
+        ...orderAccounts(1),
+        mintOut: mintY,
+        keeper: initializer.publicKey,
+        owner: initializer.publicKey,
+        oracle: oraclePda,
+        tokenProgramOut: anchor.utils.token.TOKEN_PROGRAM_ID,
+      })
+      .rpc();
+    const userYPost = await getAccount(provider.connection, userYPda);
+    expect(Number(userYPost.amount)).to.be.greaterThan(Number(userYPre.amount));
+    expect(await provider.connection.getAccountInfo(orderPda(1))).to.equal(null);
+
+    // a limit the pool cannot reach is refused, and the owner takes the escrow back
+    await program.methods
+      .placeOrder(new BN(2), new BN(10_000), new BN(1_000_000_000), new BN(0), expiresAt())
+      .accounts({ ...orderAccounts(2), mintOut: mintY, owner: initializer.publicKey })
+      .rpc();
+    try {
+      await program.methods
+        .fillOrder()
+        .accounts({
+          ...orderAccounts(2),
+          mintOut: mintY,
+          keeper: initializer.publicKey,
+          owner: initializer.publicKey,
+          oracle: oraclePda,
+          tokenProgramOut: anchor.utils.token.TOKEN_PROGRAM_ID,
+        })
+        .rpc();
+      expect.fail("the limit is out of range");
+    } catch (err) {
+      expect(String(err)).to.include("LimitNotReached");
+    }
+    const userXPre = await getAccount(provider.connection, userXPda);
+    await program.methods
+      .cancelOrder()
+      .accounts({ ...orderAccounts(2), caller: initializer.publicKey, owner: initializer.publicKey })
+      .rpc();
+    const userXPost = await getAccount(provider.connection, userXPda);
+    expect(Number(userXPost.amount) - Number(userXPre.amount)).to.equal(10_000);
+
+    // orders cannot run past a forced exit's notice
+    try {
+      await program.methods
+        .placeOrder(new BN(3), new BN(10_000), new BN(1), new BN(0), new BN(Math.floor(Date.now() / 1000) + 8 * 86_400))
+        .accounts({ ...orderAccounts(3), mintOut: mintY, owner: initializer.publicKey })
+        .rpc();
+      expect.fail("the expiry is too far out");
+    } catch (err) {
+      expect(err.error.errorCode.code).to.equal("OrderExpiryTooFar");
+    }
+
+    // anyone may clear an expired order, the escrow still goes back to the owner
+    const keeper = Keypair.generate();
+    await provider.sendAndConfirm(
+      new web3.Transaction().add(
+        SystemProgram.transfer({
+          fromPubkey: initializer.publicKey,
+          toPubkey: keeper.publicKey,
+          lamports: 100_000_000,
+        })
+      )
+    );
+    await program.methods
+      .placeOrder(new BN(3), new BN(10_000), new BN(1_000_000_000), new BN(0), new BN(Math.floor(Date.now() / 1000) + 2))
+      .accounts({ ...orderAccounts(3), mintOut: mintY, owner: initializer.publicKey })
+      .rpc();
+    const cancelByKeeper = () =>
+      program.methods
+        .cancelOrder()
+        .accounts({ ...orderAccounts(3), caller: keeper.publicKey, owner: initializer.publicKey })
+        .signers([keeper])
+        .rpc();
+    try {
+      await cancelByKeeper();
+      expect.fail("the order has not expired");
+    } catch (err) {
+      expect(err.error.errorCode.code).to.equal("OrderNotExpired");
+    }
+    await new Promise((resolve) => setTimeout(resolve, 4_000));
+    await cancelByKeeper();
+    expect(await provider.connection.getAccountInfo(orderPda(3))).to.equal(null);
+    expect(Number((await getAccount(provider.connection, userXPda)).amount)).to.equal(Number(userXPost.amount));
+  });
+
+  it("Rejects a swap past its deadline", async () => {
+    try {
+      await program.methods
+        .swap(true, new BN(1_000), new BN(1), new BN(Math.floor(Date.now() / 1000) - 600), false, 0)
+        .accounts({
+          user: initializer.publicKey,
+          mintX,
+          mintY,
+          vaultX: vaultXPda,
+          vaultY: vaultYPda,
+          userX: userXPda,
+          userY: userYPda,
+          treasuryX: treasuryXPda,
+          treasuryY: treasuryYPda,
+          config: configPda,
+          oracle: oraclePda,
+          tokenProgramX: anchor.utils.token.TOKEN_PROGRAM_ID,
+          tokenProgramY: anchor.utils.token.TOKEN_PROGRAM_ID,
+          associatedTokenProgram: anchor.utils.token.ASSOCIATED_PROGRAM_ID,
+          systemProgram: SystemProgram.programId,
+          referrer: null,
+          allowlistEntry: null,
+          nativeAccount: null,
+        })
+        .rpc();
+      expect.fail("swap should fail after its deadline");
+    } catch (err) {
+      expect(err.error.errorCode.code).to.equal("TransactionExpired");
+    }
+  });
+
+  it("Routes a swap through a list of pools", async () => {
+    const userYPre = await getAccount(provider.connection, userYPda);
+
+    // one hop: config, vault_in, vault_out, treasury_in, oracle, user_out
+    await program.methods
+      .routeSwap(new BN(5_000), new BN(1), expiresAt())
+      .accounts({
+        user: initializer.publicKey,
+        userSrc: userXPda,
+        tokenProgram: anchor.utils.token.TOKEN_PROGRAM_ID,
+        tokenProgram2022: TOKEN_2022_PROGRAM_ID,
+      })
+      .remainingAccounts([
+        { pubkey: configPda, isSigner: false, isWritable: true },
+        { pubkey: mintX, isSigner: false, isWritable: false },
+        { pubkey: mintY, isSigner: false, isWritable: false },
+        { pubkey: vaultXPda, isSigner: false, isWritable: true },
+        { pubkey: vaultYPda, isSigner: false, isWritable: true },
+        { pubkey: treasuryXPda, isSigner: false, isWritable: true },
+        { pubkey: oraclePda, isSigner: false, isWritable: true },
+        { pubkey: userYPda, isSigner: false, isWritable: true },
+      ])
+      .rpc();
+
+    const userYPost = await getAccount(provider.connection, userYPda);
+    expect(Number(userYPost.amount)).to.be.greaterThan(Number(userYPre.amount));
+  });
+
+  it("Takes and repays a flash loan in one transaction", async () => {
+    const vaultXPre = await getAccount(provider.connection, vaultXPda);
+    const flashAccounts = {
+      borrower: initializer.publicKey,
+      mintX,
+      mintY,
+      config: configPda,
+      vaultX: vaultXPda,
+      vaultY: vaultYPda,
+      borrowerToken: userXPda,
+      instructions: web3.SYSVAR_INSTRUCTIONS_PUBKEY,
+      tokenProgramX: anchor.utils.token.TOKEN_PROGRAM_ID,
+      tokenProgramY: anchor.utils.token.TOKEN_PROGRAM_ID,
+      allowlistEntry: null,
+    };
+
+    const tx = new web3.Transaction().add(
+      await program.methods.flashLoan(true, new BN(100_000)).accounts(flashAccounts).instruction(),
+      await program.methods.flashRepay().accounts(flashAccounts).instruction()
+    );
+    await provider.sendAndConfirm(tx);
+
+    // 9 bps fee on 100_000, rounded up
+    const vaultXPost = await getAccount(provider.connection, vaultXPda);
+    expect(Number(vaultXPost.amount - vaultXPre.amount)).to.equal(90);
+  });
+
+  it("Zaps in and out of the pool with a single token", async () => {
+    const zapAccounts = {
+      user: initializer.publicKey,
+      mintX,
+      mintY,
+      config: configPda,
+      mintLp: mintLpPda,
+      vaultX: vaultXPda,
+      vaultY: vaultYPda,
+      treasuryX: treasuryXPda,
+      treasuryY: treasuryYPda,
+      userX: userXPda,
+      userY: userYPda,
+      userLp: userLpPda,
+      oracle: oraclePda,
+      tokenProgram: anchor.utils.token.TOKEN_PROGRAM_ID,
+      tokenProgramX: anchor.utils.token.TOKEN_PROGRAM_ID,
+      tokenProgramY: anchor.utils.token.TOKEN_PROGRAM_ID,
+      systemProgram: SystemProgram.programId,
+      associatedTokenProgram: anchor.utils.token.ASSOCIATED_PROGRAM_ID,
+      allowlistEntry: null,
+    };
+    const lpPre = await getAccount(provider.connection, userLpPda);
+    const userXPre = await getAccount(provider.connection, userXPda);
+    const userYPre = await getAccount(provider.connection, userYPda);
+
+    await program.methods
+      .depositSingle(true, new BN(100_000), new BN(1), expiresAt())
+      .accounts(zapAccounts)
+      .rpc();
+
+    const lpMid = await getAccount(provider.connection, userLpPda);
+    const userXMid = await getAccount(provider.connection, userXPda);
+    const userYMid = await getAccount(provider.connection, userYPda);
+    const minted = lpMid.amount - lpPre.amount;
+    expect(Number(minted)).to.be.greaterThan(0);
+    // never more than offered is taken, y only ever comes back as dust
+    expect(Number(userXPre.amount - userXMid.amount)).to.be.at.most(100_000);
+    expect(Number(userYMid.amount)).to.be.at.least(Number(userYPre.amount));
+
+    await program.methods
+      .withdrawSingle(true, new BN(minted.toString()), new BN(1), expiresAt())
+      .accounts(zapAccounts)
+      .rpc();
+
+    const lpPost = await getAccount(provider.connection, userLpPda);
+    const userXPost = await getAccount(provider.connection, userXPda);
+    expect(lpPost.amount).to.equal(lpPre.amount);
+    // the round trip pays the swap fee twice
+    const received = Number(userXPost.amount - userXMid.amount);
+    expect(received).to.be.greaterThan(0);
+    expect(received).to.be.lessThan(Number(userXPre.amount - userXMid.amount));
+  });
+
+  it("Farms LP tokens for rewards", async () => {
+    const rewardMint = await createMint(provider.connection, initializer.payer, initializer.publicKey, null, 6);
+    const source = await getOrCreateAssociatedTokenAccount(
+      provider.connection,
+      initializer.payer,
+      rewardMint,
+      initializer.publicKey
+    );
+    await mintTo(provider.connection, initializer.payer, rewardMint, source.address, initializer.publicKey, 1_000_000);
+
+    const [farmPda] = PublicKey.findProgramAddressSync(
+      [Buffer.from("farm"), configPda.toBuffer()],
+      program.programId
+    );
+    await program.methods
+      .createFarm()
+      .accounts({
+        authority: initializer.publicKey,
+        config: configPda,
+        mintLp: mintLpPda,
+        rewardMint,
+        farm: farmPda,
+        tokenProgram: anchor.utils.token.TOKEN_PROGRAM_ID,
+        rewardTokenProgram: anchor.utils.token.TOKEN_PROGRAM_ID,
+      })
+      .rpc();
+    await program.methods
+      .fundFarm(new BN(1_000_000), new BN(Math.floor(Date.now() / 1000) + 1_000))
+      .accounts({
+        authority: initializer.publicKey,
+        config: configPda,
+        rewardMint,
+        farm: farmPda,
+        source: source.address,
+        rewardTokenProgram: anchor.utils.token.TOKEN_PROGRAM_ID,
+      })
+      .rpc();
+
+    const stakeAccounts = {
+      user: initializer.publicKey,
+      config: configPda,
+      mintLp: mintLpPda,
+      rewardMint,
+      farm: farmPda,
+      userLp: userLpPda,
+      userReward: source.address,
+      tokenProgram: anchor.utils.token.TOKEN_PROGRAM_ID,
+      rewardTokenProgram: anchor.utils.token.TOKEN_PROGRAM_ID,
+    };
+    const lpPre = await getAccount(provider.connection, userLpPda);
+    await program.methods.stakeLp(new BN(10_000)).accounts(stakeAccounts).rpc();
+    const farm = await program.account.farm.fetch(farmPda);
+    expect(farm.totalStaked.toNumber()).to.equal(10_000);
+
+    await new Promise((resolve) => setTimeout(resolve, 2_000));
+    const rewardPre = await getAccount(provider.connection, source.address);
+    await program.methods.claim().accounts(stakeAccounts).rpc();
+    const rewardPost = await getAccount(provider.connection, source.address);
+    expect(Number(rewardPost.amount - rewardPre.amount)).to.be.greaterThan(0);
+
+    await program.methods.unstakeLp(new BN(10_000)).accounts(stakeAccounts).rpc();
+    const lpPost = await getAccount(provider.connection, userLpPda);
+    expect(lpPost.amount).to.equal(lpPre.amount);
   });
 
   it("Withdraws LP and receives tokens", async () => {
-    const amountLp = new BN(1_000);
     const userLpPda = getAssociatedTokenAddressSync(mintLpPda, initializer.publicKey);
+    const lpPre = await getAccount(provider.connection, userLpPda);
+    const amountLp = new BN(lpPre.amount.toString());
 
     await getOrCreateAssociatedTokenAccount(
       provider.connection,
@@ -220,7 +737,7 @@ describe("initialize AMM", () => {
     );
 
     await program.methods
-      .withdraw(amountLp, new BN(1), new BN(1)) // min_x, min_y
+      .withdraw(amountLp, new BN(1), new BN(1), expiresAt(), false) // min_x, min_y
       .accounts({
         user: initializer.publicKey,
         mintX,
@@ -232,9 +749,14 @@ describe("initialize AMM", () => {
         userY: userYPda,
         userLp: userLpPda,
         config: configPda,
+        oracle: oraclePda,
         tokenProgram: anchor.utils.token.TOKEN_PROGRAM_ID,
+        tokenProgramX: anchor.utils.token.TOKEN_PROGRAM_ID,
+        tokenProgramY: anchor.utils.token.TOKEN_PROGRAM_ID,
         associatedTokenProgram: anchor.utils.token.ASSOCIATED_PROGRAM_ID,
         systemProgram: SystemProgram.programId,
+        allowlistEntry: null,
+        nativeAccount: null,
       })
       .rpc();
 
@@ -251,4 +773,826 @@ describe("initialize AMM", () => {
     expect(Number(userY.amount)).to.be.greaterThan(0);
   });
 
+  it("Lets the authority retune fees and lock the pool", async () => {
+    // the pair entry moves along with the fee tier
+    const pairAt = (fee: number) =>
+      PublicKey.findProgramAddressSync(
+        [Buffer.from("pair"), mintX.toBuffer(), mintY.toBuffer(), new BN(fee).toArrayLike(Buffer, "le", 2)],
+        program.programId
+      )[0];
+    const updateFee = (from: number, to: number) =>
+      program.methods
+        .updateFee(to)
+        .accounts({
+          authority: initializer.publicKey,
+          initializer: initializer.publicKey,
+          config: configPda,
+          pair: pairAt(from),
+          newPair: pairAt(to),
+        })
+        .rpc();
+    try {
+      await updateFee(30, 25);
+      expect.fail("25 bps is not a listed tier");
+    } catch (err) {
+      expect(err.error.errorCode.code).to.equal("InvalidFee");
+    }
+    await updateFee(30, 4);
+    expect((await program.account.config.fetch(configPda)).fee).to.equal(4);
+    expect((await program.account.pair.fetch(pairAt(4))).fee).to.equal(4);
+    expect(await provider.connection.getAccountInfo(pairAt(30))).to.equal(null);
+    await updateFee(4, 30);
+
+    await program.methods
+      .lock()
+      .accounts({ authority: initializer.publicKey, config: configPda })
+      .rpc();
+
+    let configAccount = await program.account.config.fetch(configPda);
+    expect(configAccount.fee).to.equal(30);
+    expect(configAccount.locked).to.equal(true);
+
+    try {
+      await program.methods
+        .swap(true, new BN(1_000), new BN(1), expiresAt(), false, 0)
+        .accounts({
+          user: initializer.publicKey,
+          mintX,
+          mintY,
+          vaultX: vaultXPda,
+          vaultY: vaultYPda,
+          userX: userXPda,
+          userY: userYPda,
+          treasuryX: treasuryXPda,
+          treasuryY: treasuryYPda,
+          config: configPda,
+          oracle: oraclePda,
+          tokenProgramX: anchor.utils.token.TOKEN_PROGRAM_ID,
+          tokenProgramY: anchor.utils.token.TOKEN_PROGRAM_ID,
+          associatedTokenProgram: anchor.utils.token.ASSOCIATED_PROGRAM_ID,
+          systemProgram: SystemProgram.programId,
+          referrer: null,
+          allowlistEntry: null,
+          nativeAccount: null,
+        })
+        .rpc();
+      expect.fail("swap should fail on a locked pool");
+    } catch (err) {
+      expect(err.error.errorCode.code).to.equal("PoolLocked");
+    }
+
+    await program.methods
+      .unlock()
+      .accounts({ authority: initializer.publicKey, config: configPda })
+      .rpc();
+    configAccount = await program.account.config.fetch(configPda);
+    expect(configAccount.locked).to.equal(false);
+  });
+
+  it("Registers the pool for its pair and in the pool index", async () => {
+    const [pairPda] = PublicKey.findProgramAddressSync(
+      [Buffer.from("pair"), mintX.toBuffer(), mintY.toBuffer(), new BN(30).toArrayLike(Buffer, "le", 2)],
+      program.programId
+    );
+    const pair = await program.account.pair.fetch(pairPda);
+    expect(pair.config.toBase58()).to.equal(configPda.toBase58());
+    expect(pair.fee).to.equal(30);
+
+    const [factoryPda] = PublicKey.findProgramAddressSync([Buffer.from("factory")], program.programId);
+    const factory = await program.account.factory.fetch(factoryPda);
+    expect(factory.poolCount.toNumber()).to.be.at.least(1);
+
+    const [indexPda] = PublicKey.findProgramAddressSync(
+      [Buffer.from("pool_index"), new BN(0).toArrayLike(Buffer, "le", 8)],
+      program.programId
+    );
+    const index = await program.account.poolIndex.fetch(indexPda);
+    expect(index.pools.map((pool) => pool.toBase58())).to.include(configPda.toBase58());
+
+    // a second pool on the same pair and tier is refused, one at another tier is not
+    const initializeAt = async (poolSeed: BN, fee: number) => {
+      const [poolConfig] = PublicKey.findProgramAddressSync(
+        [Buffer.from("config"), poolSeed.toArrayLike(Buffer, "le", 8)],
+        program.programId
+      );
+      await program.methods
+        .initialize(poolSeed, 6, fee, 0, initializer.publicKey, { constantProduct: {} }, new BN(0), null)
+        .accounts({
+          initializer: initializer.publicKey,
+          poolIndex: await nextPoolIndex(),
+          mintX,
+          mintY,
+          config: poolConfig,
+          tokenProgram: anchor.utils.token.TOKEN_PROGRAM_ID,
+          tokenProgramX: anchor.utils.token.TOKEN_PROGRAM_ID,
+          tokenProgramY: anchor.utils.token.TOKEN_PROGRAM_ID,
+          associatedTokenProgram: anchor.utils.token.ASSOCIATED_PROGRAM_ID,
+          systemProgram: SystemProgram.programId,
+        })
+        .rpc();
+      return poolConfig;
+    };
+    try {
+      await initializeAt(new BN(45), 30);
+      expect.fail("a pair only gets one pool per tier");
+    } catch (err) {
+      expect(String(err)).to.not.include("a pair only gets one pool per tier");
+    }
+    try {
+      await initializeAt(new BN(45), 31);
+      expect.fail("31 bps is not a listed tier");
+    } catch (err) {
+      expect(err.error.errorCode.code).to.equal("InvalidFee");
+    }
+    const volatileConfig = await initializeAt(new BN(49), 100);
+    expect((await program.account.config.fetch(volatileConfig)).fee).to.equal(100);
+  });
+
+  it("Reports a time-weighted average price", async () => {
+    const twap = await program.methods
+      .observe(1)
+      .accounts({ config: configPda, oracle: oraclePda })
+      .view();
+    console.log("✅ TWAP price x:", twap.priceX.toString());
+
+    // prices run on the reserves the latest trade left, not on live vault balances
+    const oracle = await program.account.oracle.fetch(oraclePda);
+    expect(oracle.observations.length).to.be.greaterThan(0);
+    expect(oracle.reserveX.toNumber()).to.be.greaterThan(0);
+  });
+
+  it("Initializes a pool over a Token-2022 mint with a transfer fee", async () => {
+    // 1% transfer fee on the new mint
+    // the fee mint is the pool's y side, so it has to sort after mintX
+    let feeMint = Keypair.generate();
+    while (Buffer.compare(mintX.toBuffer(), feeMint.publicKey.toBuffer()) >= 0) {
+      feeMint = Keypair.generate();
+    }
+    const mintLen = getMintLen([ExtensionType.TransferFeeConfig]);
+    const lamports = await provider.connection.getMinimumBalanceForRentExemption(mintLen);
+    const tx = new web3.Transaction().add(
+      SystemProgram.createAccount({
+        fromPubkey: initializer.publicKey,
+        newAccountPubkey: feeMint.publicKey,
+        space: mintLen,
+        lamports,
+        programId: TOKEN_2022_PROGRAM_ID,
+      }),
+      createInitializeTransferFeeConfigInstruction(
+        feeMint.publicKey,
+        initializer.publicKey,
+        initializer.publicKey,
+        100,
+        BigInt(1_000_000),
+        TOKEN_2022_PROGRAM_ID
+      ),
+      createInitializeMintInstruction(
+        feeMint.publicKey,
+        6,
+        initializer.publicKey,
+        null,
+        TOKEN_2022_PROGRAM_ID
+      )
+    );
+    await provider.sendAndConfirm(tx, [feeMint]);
+
+    const seed2022 = new BN(43);
+    const [config2022] = PublicKey.findProgramAddressSync(
+      [Buffer.from("config"), seed2022.toArrayLike(Buffer, "le", 8)],
+      program.programId
+    );
+    const vault2022 = getAssociatedTokenAddressSync(
+      feeMint.publicKey,
+      config2022,
+      true,
+      TOKEN_2022_PROGRAM_ID
+    );
+
+    await program.methods
+      .initialize(seed2022, 6, 30, 0, initializer.publicKey, { constantProduct: {} }, new BN(0), null)
+      .accounts({
+        initializer: initializer.publicKey,
+        poolIndex: await nextPoolIndex(),
+        mintX,
+        mintY: feeMint.publicKey,
+        config: config2022,
+        vaultY: vault2022,
+        tokenProgram: anchor.utils.token.TOKEN_PROGRAM_ID,
+        tokenProgramX: anchor.utils.token.TOKEN_PROGRAM_ID,
+        tokenProgramY: TOKEN_2022_PROGRAM_ID,
+        associatedTokenProgram: anchor.utils.token.ASSOCIATED_PROGRAM_ID,
+        systemProgram: SystemProgram.programId,
+      })
+      .rpc();
+
+    const vault = await getAccount(provider.connection, vault2022, undefined, TOKEN_2022_PROGRAM_ID);
+    expect(vault.owner.toBase58()).to.equal(config2022.toBase58());
+  });
+
+  it("Initializes a StableSwap pool and ramps its amplification", async () => {
+    // a pair gets one pool, so the stable pool needs mints of its own
+    let stableX = await createMint(provider.connection, initializer.payer, initializer.publicKey, null, 6);
+    let stableY = await createMint(provider.connection, initializer.payer, initializer.publicKey, null, 6);
+    if (Buffer.compare(stableX.toBuffer(), stableY.toBuffer()) > 0) {
+      [stableX, stableY] = [stableY, stableX];
+    }
+    const stableSeed = new BN(44);
+    const [stableConfig] = PublicKey.findProgramAddressSync(
+      [Buffer.from("config"), stableSeed.toArrayLike(Buffer, "le", 8)],
+      program.programId
+    );
+
+    await program.methods
+      .initialize(stableSeed, 6, 4, 0, initializer.publicKey, { stableSwap: {} }, new BN(100), null)
+      .accounts({
+        initializer: initializer.publicKey,
+        poolIndex: await nextPoolIndex(),
+        mintX: stableX,
+        mintY: stableY,
+        config: stableConfig,
+        tokenProgram: anchor.utils.token.TOKEN_PROGRAM_ID,
+        tokenProgramX: anchor.utils.token.TOKEN_PROGRAM_ID,
+        tokenProgramY: anchor.utils.token.TOKEN_PROGRAM_ID,
+        associatedTokenProgram: anchor.utils.token.ASSOCIATED_PROGRAM_ID,
+        systemProgram: SystemProgram.programId,
+      })
+      .rpc();
+
+    const rampEnd = new BN(Math.floor(Date.now() / 1000) + 2 * 86_400);
+    await program.methods
+      .rampAmp(new BN(200), rampEnd)
+      .accounts({ authority: initializer.publicKey, config: stableConfig })
+      .rpc();
+
+    const configAccount = await program.account.config.fetch(stableConfig);
+    expect(configAccount.poolType).to.deep.equal({ stableSwap: {} });
+    expect(configAccount.ampTarget.toNumber()).to.equal(200);
+  });
+
+  it("Mints LP in its own decimals for a 9/6-decimal pair", async () => {
+    let solLike = await createMint(provider.connection, initializer.payer, initializer.publicKey, null, 9);
+    let usdLike = await createMint(provider.connection, initializer.payer, initializer.publicKey, null, 6);
+    const [pairX, pairY] =
+      Buffer.compare(solLike.toBuffer(), usdLike.toBuffer()) < 0 ? [solLike, usdLike] : [usdLike, solLike];
+    const decimalsSeed = new BN(46);
+    const [decimalsConfig] = PublicKey.findProgramAddressSync(
+      [Buffer.from("config"), decimalsSeed.toArrayLike(Buffer, "le", 8)],
+      program.programId
+    );
+
+    await program.methods
+      .initialize(decimalsSeed, 9, 30, 0, initializer.publicKey, { constantProduct: {} }, new BN(0), null)
+      .accounts({
+        initializer: initializer.publicKey,
+        poolIndex: await nextPoolIndex(),
+        mintX: pairX,
+        mintY: pairY,
+        config: decimalsConfig,
+        tokenProgram: anchor.utils.token.TOKEN_PROGRAM_ID,
+        tokenProgramX: anchor.utils.token.TOKEN_PROGRAM_ID,
+        tokenProgramY: anchor.utils.token.TOKEN_PROGRAM_ID,
+        associatedTokenProgram: anchor.utils.token.ASSOCIATED_PROGRAM_ID,
+        systemProgram: SystemProgram.programId,
+      })
+      .rpc();
+
+    // 1 whole SOL-like token against 100 whole USD-like tokens
+    const amounts = new Map([
+      [solLike.toBase58(), 1_000_000_000],
+      [usdLike.toBase58(), 100_000_000],
+    ]);
+    for (const mint of [pairX, pairY]) {
+      const account = await getOrCreateAssociatedTokenAccount(
+        provider.connection,
+        initializer.payer,
+        mint,
+        initializer.publicKey
+      );
+      await mintTo(provider.connection, initializer.payer, mint, account.address, initializer.publicKey, amounts.get(mint.toBase58()));
+    }
+
+    await program.methods
+      .deposit(new BN(1), new BN(amounts.get(pairX.toBase58())), new BN(amounts.get(pairY.toBase58())), expiresAt(), false)
+      .accounts({
+        user: initializer.publicKey,
+        mintX: pairX,
+        mintY: pairY,
+        config: decimalsConfig,
+        tokenProgram: anchor.utils.token.TOKEN_PROGRAM_ID,
+        tokenProgramX: anchor.utils.token.TOKEN_PROGRAM_ID,
+        tokenProgramY: anchor.utils.token.TOKEN_PROGRAM_ID,
+        associatedTokenProgram: anchor.utils.token.ASSOCIATED_PROGRAM_ID,
+        systemProgram: SystemProgram.programId,
+        allowlistEntry: null,
+        nativeAccount: null,
+      })
+      .rpc();
+
+    const [decimalsLp] = PublicKey.findProgramAddressSync(
+      [Buffer.from("lp"), decimalsConfig.toBuffer()],
+      program.programId
+    );
+    expect((await getMint(provider.connection, decimalsLp)).decimals).to.equal(9);
+    // sqrt(1 * 100) = 10 whole LP at 9 decimals, less the locked minimum
+    const userLp = await getAccount(
+      provider.connection,
+      getAssociatedTokenAddressSync(decimalsLp, initializer.publicKey)
+    );
+    expect(Number(userLp.amount)).to.equal(10_000_000_000 - 1_000);
+  });
+
+  it("Pays and receives native SOL in native mode", async () => {
+    const other = await createMint(provider.connection, initializer.payer, initializer.publicKey, null, 9);
+    const [pairX, pairY] =
+      Buffer.compare(NATIVE_MINT.toBuffer(), other.toBuffer()) < 0 ? [NATIVE_MINT, other] : [other, NATIVE_MINT];
+    const solIsX = pairX.equals(NATIVE_MINT);
+    const nativeSeed = new BN(47);
+    const [nativeConfig] = PublicKey.findProgramAddressSync(
+      [Buffer.from("config"), nativeSeed.toArrayLike(Buffer, "le", 8)],
+      program.programId
+    );
+    const accounts = {
+      mintX: pairX,
+      mintY: pairY,
+      config: nativeConfig,
+      tokenProgram: anchor.utils.token.TOKEN_PROGRAM_ID,
+      tokenProgramX: anchor.utils.token.TOKEN_PROGRAM_ID,
+      tokenProgramY: anchor.utils.token.TOKEN_PROGRAM_ID,
+      associatedTokenProgram: anchor.utils.token.ASSOCIATED_PROGRAM_ID,
+      systemProgram: SystemProgram.programId,
+    };
+
+    await program.methods
+      .initialize(nativeSeed, 9, 30, 0, initializer.publicKey, { constantProduct: {} }, new BN(0), null)
+      .accounts({ ...accounts, initializer: initializer.publicKey, poolIndex: await nextPoolIndex() })
+      .rpc();
+
+    const userOther = await getOrCreateAssociatedTokenAccount(
+      provider.connection,
+      initializer.payer,
+      other,
+      initializer.publicKey
+    );
+    await mintTo(provider.connection, initializer.payer, other, userOther.address, initializer.publicKey, 1_000_000_000);
+
+    // wSOL the wallet already holds stays put, native mode only goes through a temporary account
+    const wsol = await createWrappedNativeAccount(
+      provider.connection,
+      initializer.payer,
+      initializer.publicKey,
+      5_000_000
+    );
+    const [nativeAccount] = PublicKey.findProgramAddressSync(
+      [Buffer.from("wsol"), initializer.publicKey.toBuffer()],
+      program.programId
+    );
+    // the wSOL side's user account is left out
+    const userAccounts = {
+      userX: solIsX ? null : userOther.address,
+      userY: solIsX ? userOther.address : null,
+    };
+
+    // one SOL straight from the wallet's lamports against 1_000 of the other token
+    await program.methods
+      .deposit(new BN(1), new BN(1_000_000_000), new BN(1_000_000_000), expiresAt(), true)
+      .accounts({ ...accounts, ...userAccounts, user: initializer.publicKey, allowlistEntry: null, nativeAccount })
+      .rpc();
+
+    const vaultSol = getAssociatedTokenAddressSync(NATIVE_MINT, nativeConfig, true);
+    expect(Number((await getAccount(provider.connection, vaultSol)).amount)).to.equal(1_000_000_000);
+    expect(Number((await getAccount(provider.connection, wsol)).amount)).to.equal(5_000_000);
+    expect(await provider.connection.getAccountInfo(nativeAccount)).to.equal(null);
+
+    // the other token in, lamports out
+    const lamportsPre = await provider.connection.getBalance(initializer.publicKey);
+    await program.methods
+      .swap(!solIsX, new BN(100_000_000), new BN(1), expiresAt(), true, 0)
+      .accounts({
+        ...accounts,
+        ...userAccounts,
+        user: initializer.publicKey,
+        referrer: null,
+        allowlistEntry: null,
+        nativeAccount,
+      })
+      .rpc();
+
+    expect(Number((await getAccount(provider.connection, wsol)).amount)).to.equal(5_000_000);
+    expect(await provider.connection.getAccountInfo(nativeAccount)).to.equal(null);
+    expect(await provider.connection.getBalance(initializer.publicKey)).to.be.greaterThan(lamportsPre);
+  });
+
+  it("Launches on a bonding curve and graduates to constant product", async () => {
+    const token = await createMint(provider.connection, initializer.payer, initializer.publicKey, null, 6);
+    const quote = await createMint(provider.connection, initializer.payer, initializer.publicKey, null, 6);
+    const [pairX, pairY] = Buffer.compare(token.toBuffer(), quote.toBuffer()) < 0 ? [token, quote] : [quote, token];
+    const tokenIsX = pairX.equals(token);
+    const launchSeed = new BN(48);
+    const [launchConfig] = PublicKey.findProgramAddressSync(
+      [Buffer.from("config"), launchSeed.toArrayLike(Buffer, "le", 8)],
+      program.programId
+    );
+    const programs = {
+      tokenProgram: anchor.utils.token.TOKEN_PROGRAM_ID,
+      tokenProgramX: anchor.utils.token.TOKEN_PROGRAM_ID,
+      tokenProgramY: anchor.utils.token.TOKEN_PROGRAM_ID,
+    };
+    const accounts = {
+      ...programs,
+      mintX: pairX,
+      mintY: pairY,
+      config: launchConfig,
+      associatedTokenProgram: anchor.utils.token.ASSOCIATED_PROGRAM_ID,
+      systemProgram: SystemProgram.programId,
+    };
+
+    // a launch without virtual liquidity on both sides has nothing to price against
+    const zero = new BN(0);
+    try {
+      await program.methods
+        .initialize(launchSeed, 6, 30, 0, initializer.publicKey, { launch: {} }, new BN(0), {
+          virtualX: zero,
+          virtualY: new BN(30_000_000),
+          targetX: zero,
+          targetY: new BN(10_000_000),
+        })
+        .accounts({ ...accounts, initializer: initializer.publicKey, poolIndex: await nextPoolIndex() })
+        .rpc();
+      expect.fail("launch without virtual reserves should fail");
+    } catch (err) {
+      expect(err.error.errorCode.code).to.equal("InvalidAmount");
+    }
+
+    // 30 quote of virtual liquidity prices the launch until 10 real quote have come in
+    const virtualToken = new BN(1_000_000);
+    const launch = tokenIsX
+      ? { virtualX: virtualToken, virtualY: new BN(30_000_000), targetX: zero, targetY: new BN(10_000_000) }
+      : { virtualX: new BN(30_000_000), virtualY: virtualToken, targetX: new BN(10_000_000), targetY: zero };
+    await program.methods
+      .initialize(launchSeed, 6, 30, 0, initializer.publicKey, { launch: {} }, new BN(0), launch)
+      .accounts({ ...accounts, initializer: initializer.publicKey, poolIndex: await nextPoolIndex() })
+      .rpc();
+
+    // the whole launched supply sits in its vault, no LP exists until graduation
+    await mintTo(
+      provider.connection,
+      initializer.payer,
+      token,
+      getAssociatedTokenAddressSync(token, launchConfig, true),
+      initializer.publicKey,
+      1_000_000_000
+    );
+    const userQuote = await getOrCreateAssociatedTokenAccount(
+      provider.connection,
+      initializer.payer,
+      quote,
+      initializer.publicKey
+    );
+    await mintTo(provider.connection, initializer.payer, quote, userQuote.address, initializer.publicKey, 20_000_000);
+
+    const graduate = () =>
+      program.methods
+        .graduate()
+        .accounts({ ...programs, caller: initializer.publicKey, mintX: pairX, mintY: pairY, config: launchConfig })
+        .rpc();
+    try {
+      await graduate();
+      expect.fail("graduated before reaching the target");
+    } catch (err) {
+      expect(err.error.errorCode.code).to.equal("LaunchTargetNotReached");
+    }
+
+    await program.methods
+      .swap(!tokenIsX, new BN(20_000_000), new BN(1), expiresAt(), false, 0)
+      .accounts({ ...accounts, user: initializer.publicKey, referrer: null, allowlistEntry: null, nativeAccount: null })
+      .rpc();
+    const bought = await getAccount(provider.connection, getAssociatedTokenAddressSync(token, initializer.publicKey));
+    expect(Number(bought.amount)).to.be.greaterThan(0);
+
+    await graduate();
+    const config = await program.account.config.fetch(launchConfig);
+    expect(config.poolType).to.deep.equal({ constantProduct: {} });
+    const [launchLocked] = PublicKey.findProgramAddressSync(
+      [Buffer.from("locked"), launchConfig.toBuffer()],
+      program.programId
+    );
+    expect(Number((await getAccount(provider.connection, launchLocked)).amount)).to.be.greaterThan(0);
+
+    // with all of its LP locked, nothing the pool holds belongs to the authority
+    try {
+      await program.methods
+        .closePool()
+        .accounts({
+          ...programs,
+          authority: initializer.publicKey,
+          initializer: initializer.publicKey,
+          mintX: pairX,
+          mintY: pairY,
+          config: launchConfig,
+          pair: PublicKey.findProgramAddressSync(
+            [Buffer.from("pair"), pairX.toBuffer(), pairY.toBuffer(), new BN(30).toArrayLike(Buffer, "le", 2)],
+            program.programId
+          )[0],
+          lockedLp: launchLocked,
+          poolIndex: PublicKey.findProgramAddressSync(
+            [Buffer.from("pool_index"), new BN(0).toArrayLike(Buffer, "le", 8)],
+            program.programId
+          )[0],
+          destinationX: getAssociatedTokenAddressSync(pairX, initializer.publicKey),
+          destinationY: getAssociatedTokenAddressSync(pairY, initializer.publicKey),
+          exitX: null,
+          exitY: null,
+        })
+        .rpc();
+      expect.fail("a graduated pool cannot be closed");
+    } catch (err) {
+      expect(err.error.errorCode.code).to.equal("LiquidityLocked");
+    }
+  });
+
+  it("Raises the fee with volatility in dynamic fee mode", async () => {
+    let tokenA = await createMint(provider.connection, initializer.payer, initializer.publicKey, null, 6);
+    let tokenB = await createMint(provider.connection, initializer.payer, initializer.publicKey, null, 6);
+    const [pairX, pairY] = Buffer.compare(tokenA.toBuffer(), tokenB.toBuffer()) < 0 ? [tokenA, tokenB] : [tokenB, tokenA];
+    const dynamicSeed = new BN(50);
+    const [dynamicConfig] = PublicKey.findProgramAddressSync(
+      [Buffer.from("config"), dynamicSeed.toArrayLike(Buffer, "le", 8)],
+      program.programId
+    );
+    const accounts = {
+      mintX: pairX,
+      mintY: pairY,
+      config: dynamicConfig,
+      tokenProgram: anchor.utils.token.TOKEN_PROGRAM_ID,
+      tokenProgramX: anchor.utils.token.TOKEN_PROGRAM_ID,
+      tokenProgramY: anchor.utils.token.TOKEN_PROGRAM_ID,
+      associatedTokenProgram: anchor.utils.token.ASSOCIATED_PROGRAM_ID,
+      systemProgram: SystemProgram.programId,
+    };
+    await program.methods
+      .initialize(dynamicSeed, 6, 30, 0, initializer.publicKey, { constantProduct: {} }, new BN(0), null)
+      .accounts({ ...accounts, initializer: initializer.publicKey, poolIndex: await nextPoolIndex() })
+      .rpc();
+    for (const mint of [pairX, pairY]) {
+      const account = await getOrCreateAssociatedTokenAccount(
+        provider.connection,
+        initializer.payer,
+        mint,
+        initializer.publicKey
+      );
+      await mintTo(provider.connection, initializer.payer, mint, account.address, initializer.publicKey, 1_100_000_000);
+    }
+    await program.methods
+      .deposit(new BN(1), new BN(1_000_000_000), new BN(1_000_000_000), expiresAt(), false)
+      .accounts({ ...accounts, user: initializer.publicKey, allowlistEntry: null, nativeAccount: null })
+      .rpc();
+
+    const update = { authority: initializer.publicKey, config: dynamicConfig };
+    await program.methods.enableDynamicFee(100).accounts(update).rpc();
+    let dynamic = await program.account.config.fetch(dynamicConfig);
+    expect(dynamic.fee).to.equal(30);
+    expect(dynamic.dynamicFee.fee).to.equal(30);
+    try {
+      const pairAt = (fee: number) =>
+        PublicKey.findProgramAddressSync(
+          [Buffer.from("pair"), pairX.toBuffer(), pairY.toBuffer(), new BN(fee).toArrayLike(Buffer, "le", 2)],
+          program.programId
+        )[0];
+      await program.methods
+        .updateFee(100)
+        .accounts({ ...update, initializer: initializer.publicKey, pair: pairAt(30), newPair: pairAt(100) })
+        .rpc();
+      expect.fail("the fee is set by volatility in dynamic mode");
+    } catch (err) {
+      expect(err.error.errorCode.code).to.equal("InvalidFee");
+    }
+
+    // a 10% trade moves the price ~17%, which lifts the fee to its cap for the next swap
+    const tx = await program.methods
+      .swap(true, new BN(100_000_000), new BN(1), expiresAt(), false, 0)
+      .accounts({ ...accounts, user: initializer.publicKey, referrer: null, allowlistEntry: null, nativeAccount: null })
+      .rpc({ commitment: "confirmed" });
+    const txInfo = await provider.connection.getTransaction(tx, {
+      commitment: "confirmed",
+      maxSupportedTransactionVersion: 0,
+    });
+    const parser = new anchor.EventParser(program.programId, program.coder);
+    const swapped = [...parser.parseLogs(txInfo.meta.logMessages)].find((e) => e.name === "swapped");
+    expect(swapped.data.feeBps).to.equal(30);
+    // the tier fee stays the floor, the lifted fee lives in the dynamic fee state
+    const config = await program.account.config.fetch(dynamicConfig);
+    expect(config.fee).to.equal(30);
+    expect(config.dynamicFee.fee).to.equal(100);
+    expect(config.dynamicFee.volatility.toNumber()).to.be.greaterThan(0);
+
+    await program.methods.disableDynamicFee().accounts(update).rpc();
+    dynamic = await program.account.config.fetch(dynamicConfig);
+    expect(dynamic.fee).to.equal(30);
+    expect(dynamic.dynamicFee.enabled).to.equal(false);
+  });
+
+  it("Only lets allowlisted wallets trade on a permissioned pool", async () => {
+    let tokenA = await createMint(provider.connection, initializer.payer, initializer.publicKey, null, 6);
+    let tokenB = await createMint(provider.connection, initializer.payer, initializer.publicKey, null, 6);
+    const [pairX, pairY] = Buffer.compare(tokenA.toBuffer(), tokenB.toBuffer()) < 0 ? [tokenA, tokenB] : [tokenB, tokenA];
+    const permissionedSeed = new BN(51);
+    const [permissionedConfig] = PublicKey.findProgramAddressSync(
+      [Buffer.from("config"), permissionedSeed.toArrayLike(Buffer, "le", 8)],
+      program.programId
+    );
+    const accounts = {
+      mintX: pairX,
+      mintY: pairY,
+      config: permissionedConfig,
+      tokenProgram: anchor.utils.token.TOKEN_PROGRAM_ID,
+      tokenProgramX: anchor.utils.token.TOKEN_PROGRAM_ID,
+      tokenProgramY: anchor.utils.token.TOKEN_PROGRAM_ID,
+      associatedTokenProgram: anchor.utils.token.ASSOCIATED_PROGRAM_ID,
+      systemProgram: SystemProgram.programId,
+    };
+    await program.methods
+      .initialize(permissionedSeed, 6, 30, 0, initializer.publicKey, { constantProduct: {} }, new BN(0), null)
+      .accounts({ ...accounts, initializer: initializer.publicKey, poolIndex: await nextPoolIndex() })
+      .rpc();
+    for (const mint of [pairX, pairY]) {
+      const account = await getOrCreateAssociatedTokenAccount(
+        provider.connection,
+        initializer.payer,
+        mint,
+        initializer.publicKey
+      );
+      await mintTo(provider.connection, initializer.payer, mint, account.address, initializer.publicKey, 1_000_000_000);
+    }
+
+    const update = { authority: initializer.publicKey, config: permissionedConfig };
+    await program.methods.enableAllowlist().accounts(update).rpc();
+    const deposit = (allowlistEntry: PublicKey | null) =>
+      program.methods
+        .deposit(new BN(1), new BN(100_000_000), new BN(100_000_000), expiresAt(), false)
+        .accounts({ ...accounts, user: initializer.publicKey, allowlistEntry, nativeAccount: null })
+        .rpc();
+    try {
+      await deposit(null);
+      expect.fail("the wallet is not allowlisted yet");
+    } catch (err) {
+      expect(err.error.errorCode.code).to.equal("NotAllowlisted");
+    }
+
+    const [entry] = PublicKey.findProgramAddressSync(
+      [Buffer.from("allowlist"), permissionedConfig.toBuffer(), initializer.publicKey.toBuffer()],
+      program.programId
+    );
+    await program.methods
+      .addToAllowlist(initializer.publicKey)
+      .accounts({ authority: initializer.publicKey, config: permissionedConfig })
+      .rpc();
+    await deposit(entry);
+    await program.methods
+      .swap(true, new BN(1_000_000), new BN(1), expiresAt(), false, 0)
+      .accounts({ ...accounts, user: initializer.publicKey, referrer: null, allowlistEntry: entry, nativeAccount: null })
+      .rpc();
+
+    await program.methods
+      .removeFromAllowlist()
+      .accounts({ authority: initializer.publicKey, config: permissionedConfig, entry })
+      .rpc();
+    expect(await provider.connection.getAccountInfo(entry)).to.equal(null);
+    try {
+      await program.methods
+        .swap(true, new BN(1_000_000), new BN(1), expiresAt(), false, 0)
+        .accounts({ ...accounts, user: initializer.publicKey, referrer: null, allowlistEntry: null, nativeAccount: null })
+        .rpc();
+      expect.fail("the wallet was removed from the allowlist");
+    } catch (err) {
+      expect(err.error.errorCode.code).to.equal("NotAllowlisted");
+    }
+
+    // flash loans are trading too
+    const flashAccounts = {
+      borrower: initializer.publicKey,
+      mintX: pairX,
+      mintY: pairY,
+      config: permissionedConfig,
+      vaultX: getAssociatedTokenAddressSync(pairX, permissionedConfig, true),
+      vaultY: getAssociatedTokenAddressSync(pairY, permissionedConfig, true),
+      borrowerToken: getAssociatedTokenAddressSync(pairX, initializer.publicKey),
+      instructions: web3.SYSVAR_INSTRUCTIONS_PUBKEY,
+      tokenProgramX: anchor.utils.token.TOKEN_PROGRAM_ID,
+      tokenProgramY: anchor.utils.token.TOKEN_PROGRAM_ID,
+      allowlistEntry: null,
+    };
+    try {
+      await program.methods
+        .flashLoan(true, new BN(100_000))
+        .accounts(flashAccounts)
+        .postInstructions([await program.methods.flashRepay().accounts(flashAccounts).instruction()])
+        .rpc();
+      expect.fail("the borrower is not allowlisted");
+    } catch (err) {
+      expect(err.error.errorCode.code).to.equal("NotAllowlisted");
+    }
+  });
+
+  it("Closes a drained pool and returns its rent to the initializer", async () => {
+    // the 100 bps pool on the main pair never took a deposit
+    const volatileSeed = new BN(49);
+    const [volatileConfig] = PublicKey.findProgramAddressSync(
+      [Buffer.from("config"), volatileSeed.toArrayLike(Buffer, "le", 8)],
+      program.programId
+    );
+    const [indexPda] = PublicKey.findProgramAddressSync(
+      [Buffer.from("pool_index"), new BN(0).toArrayLike(Buffer, "le", 8)],
+      program.programId
+    );
+    const closeAccounts = (config: PublicKey, fee: number) => ({
+      authority: initializer.publicKey,
+      initializer: initializer.publicKey,
+      mintX,
+      mintY,
+      config,
+      pair: PublicKey.findProgramAddressSync(
+        [Buffer.from("pair"), mintX.toBuffer(), mintY.toBuffer(), new BN(fee).toArrayLike(Buffer, "le", 2)],
+        program.programId
+      )[0],
+      lockedLp: PublicKey.findProgramAddressSync([Buffer.from("locked"), config.toBuffer()], program.programId)[0],
+      poolIndex: indexPda,
+      // the sweep only ever goes to the authority's own token accounts
+      destinationX: userXPda,
+      destinationY: userYPda,
+      // only created when LPs are left after a forced exit
+      exitX: null,
+      exitY: null,
+      tokenProgram: anchor.utils.token.TOKEN_PROGRAM_ID,
+      tokenProgramX: anchor.utils.token.TOKEN_PROGRAM_ID,
+      tokenProgramY: anchor.utils.token.TOKEN_PROGRAM_ID,
+    });
+
+    const lamportsPre = await provider.connection.getBalance(initializer.publicKey);
+    await program.methods.closePool().accounts(closeAccounts(volatileConfig, 100)).rpc();
+    expect(await provider.connection.getAccountInfo(volatileConfig)).to.equal(null);
+    expect(await provider.connection.getAccountInfo(closeAccounts(volatileConfig, 100).lockedLp)).to.equal(null);
+    // clients listing pools no longer see it
+    const index = await program.account.poolIndex.fetch(indexPda);
+    expect(index.pools.map((pool) => pool.toBase58())).to.not.include(volatileConfig.toBase58());
+    expect(
+      await provider.connection.getAccountInfo(getAssociatedTokenAddressSync(mintX, volatileConfig, true))
+    ).to.equal(null);
+    expect(await provider.connection.getBalance(initializer.publicKey)).to.be.greaterThan(lamportsPre);
+
+    // a forced exit stops deposits, but the farm's stakes and rewards still need the main pool
+    await program.methods
+      .startForcedExit()
+      .accounts({ authority: initializer.publicKey, config: configPda })
+      .rpc();
+    try {
+      await program.methods.closePool().accounts(closeAccounts(configPda, 30)).rpc();
+      expect.fail("the pool has a farm");
+    } catch (err) {
+      expect(err.error.errorCode.code).to.equal("FarmActive");
+    }
+
+    // the farm closes once every stake is paid out and closed
+    const [farmPda] = PublicKey.findProgramAddressSync([Buffer.from("farm"), configPda.toBuffer()], program.programId);
+    const { rewardMint } = await program.account.farm.fetch(farmPda);
+    const rewardSource = getAssociatedTokenAddressSync(rewardMint, initializer.publicKey);
+    const farmAccounts = {
+      config: configPda,
+      mintLp: mintLpPda,
+      rewardMint,
+      farm: farmPda,
+      tokenProgram: anchor.utils.token.TOKEN_PROGRAM_ID,
+      rewardTokenProgram: anchor.utils.token.TOKEN_PROGRAM_ID,
+    };
+    const closeFarm = () =>
+      program.methods
+        .closeFarm()
+        .accounts({ ...farmAccounts, authority: initializer.publicKey, destination: rewardSource })
+        .rpc();
+    try {
+      await closeFarm();
+      expect.fail("the farm has an open stake");
+    } catch (err) {
+      expect(err.error.errorCode.code).to.equal("StakesOpen");
+    }
+    await program.methods
+      .closeStake()
+      .accounts({ ...farmAccounts, caller: initializer.publicKey, owner: initializer.publicKey })
+      .rpc();
+    const rewardPre = await getAccount(provider.connection, rewardSource);
+    await closeFarm();
+    expect(await provider.connection.getAccountInfo(farmPda)).to.equal(null);
+    // what was never emitted goes back to the authority
+    expect(Number((await getAccount(provider.connection, rewardSource)).amount)).to.be.greaterThan(
+      Number(rewardPre.amount)
+    );
+
+    // LPs still hold their share until the forced exit's notice runs out
+    try {
+      await program.methods.closePool().accounts(closeAccounts(configPda, 30)).rpc();
+      expect.fail("LPs are still in the pool");
+    } catch (err) {
+      expect(err.error.errorCode.code).to.equal("PoolNotDrained");
+    }
+  });
+
 });